@@ -0,0 +1,23 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use solution::{Decoded, Packet};
+
+fuzz_target!(|data: &[u8]| {
+    let mut remaining = data;
+    while let Ok((packet, remainder)) = Packet::deserialize(remaining) {
+        let mut buf = vec![0; packet.encoded_len()];
+        packet.serialize_into(&mut buf).unwrap();
+        assert_eq!(&remaining[..buf.len()], &buf[..]);
+        remaining = remainder;
+    }
+
+    if let Ok((packet, _)) = Packet::deserialize_unverified(data) {
+        let _ = packet.verify_checksum();
+        let _ = packet.to_string();
+    }
+
+    if let Ok(Decoded::Incomplete(needed)) = Packet::decode(data) {
+        assert!(needed > 0);
+    }
+});
@@ -0,0 +1,38 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use solution::{from_packet_data_lossy, PacketIter, Packetable};
+
+fuzz_target!(|data: &[u8]| {
+    let bytes = Vec::<u8>::from_packet_data(data);
+    if let Ok(text) = String::from_packet_data(data) {
+        assert_eq!(Ok(text.as_bytes()), bytes.as_deref());
+    }
+    // Repairing only ever runs on data that does not decode as it is.
+    let repaired = Vec::<u8>::from_packet_data_with_repair(data);
+    if bytes.is_ok() {
+        assert_eq!(repaired, bytes);
+    }
+
+    if let Ok((text, invalid_ranges)) = from_packet_data_lossy(data) {
+        let strict = String::from_packet_data(data);
+        assert_eq!(invalid_ranges.is_empty(), strict.is_ok());
+        if let Ok(strict) = strict {
+            assert_eq!(text, strict);
+        }
+    }
+
+    if let Ok(chunks) = PacketIter::new(data).payload_chunks() {
+        // Compressed chunks are handed out as they are, `from_packet_data` decompresses them.
+        if !chunks.is_compressed() {
+            let joined: Vec<u8> = chunks.flat_map(|chunk| chunk.iter().copied()).collect();
+            assert_eq!(Ok(joined), bytes);
+        }
+    }
+
+    let mut remaining = data;
+    while let Ok((_, rest)) = Vec::<u8>::next_message(remaining) {
+        assert!(rest.len() < remaining.len());
+        remaining = rest;
+    }
+});
@@ -0,0 +1,43 @@
+#![no_main]
+
+use std::io::Read;
+
+use libfuzzer_sys::fuzz_target;
+use solution::{PacketDecoder, PacketReader, Recovered, Resync, StreamDump};
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks the chunk size, so that packets get split at every position.
+    let (chunk_size, stream) = match data.split_first() {
+        Some((&size, stream)) => (size as usize + 1, stream),
+        None => return,
+    };
+
+    let mut decoder = PacketDecoder::new();
+    for chunk in stream.chunks(chunk_size) {
+        let mut failed = decoder.feed(chunk).any(|result| result.is_err());
+        while failed {
+            decoder.resync();
+            failed = decoder.feed(&[]).any(|result| result.is_err());
+        }
+        let _ = decoder.needed();
+    }
+
+    let mut offset = 0;
+    for recovered in Resync::new(stream) {
+        match recovered {
+            Recovered::Packet(packet) => offset += packet.encoded_len(),
+            Recovered::Skipped(skipped) => {
+                assert_eq!(skipped.range.start, offset);
+                offset = skipped.range.end;
+            }
+        }
+    }
+    assert_eq!(offset, stream.len());
+
+    let mut reader = PacketReader::new(stream);
+    while let Ok(Some(_)) = reader.read_message() {}
+    let mut reader = PacketReader::new(stream);
+    let _ = reader.read_to_end(&mut Vec::new());
+
+    let _ = StreamDump::new(stream).to_string();
+});
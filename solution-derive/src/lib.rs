@@ -0,0 +1,207 @@
+//! `#[derive(Packetable)]` for structs and enums.
+//!
+//! The generated code implements `solution::PacketField` field by field and
+//! `solution::Packetable` on top of it, using the layout described in `solution::encoding`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Ident, Index};
+
+#[proc_macro_derive(Packetable)]
+pub fn derive_packetable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = input.ident.clone();
+
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::solution::PacketField));
+    }
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let (encode, decode) = match &input.data {
+        Data::Struct(data) => expand_struct(&name, &data.fields),
+        Data::Enum(data) => {
+            let mut encode_arms = Vec::new();
+            let mut decode_arms = Vec::new();
+
+            for (index, variant) in data.variants.iter().enumerate() {
+                let index = index as u32;
+                let variant_name = &variant.ident;
+                let path = format!("{}::{}", name, variant_name);
+                let bindings = field_bindings(&variant.fields);
+
+                let pattern = match &variant.fields {
+                    Fields::Named(_) => quote! { { #(#bindings),* } },
+                    Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+                    Fields::Unit => quote! {},
+                };
+                let field_names = field_names(&path, &variant.fields);
+                let constructor = construct(
+                    quote! { #name::#variant_name },
+                    &variant.fields,
+                    &field_names,
+                );
+
+                encode_arms.push(quote! {
+                    #name::#variant_name #pattern => {
+                        ::solution::PacketField::encode_field(&#index, out)?;
+                        #( ::solution::PacketField::encode_field(#bindings, out)?; )*
+                        Ok(())
+                    }
+                });
+                decode_arms.push(quote! {
+                    #index => Ok(#constructor),
+                });
+            }
+
+            let name_string = name.to_string();
+            let encode = quote! {
+                match self {
+                    #(#encode_arms)*
+                }
+            };
+            let decode = quote! {
+                let variant: u32 = ::solution::encoding::decode_named(input, #name_string)?;
+                match variant {
+                    #(#decode_arms)*
+                    _ => Err(::solution::PacketErrorKind::InvalidField(#name_string).into()),
+                }
+            };
+
+            (encode, decode)
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "Packetable cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::solution::PacketField for #name #type_generics #where_clause {
+            #[allow(unused_variables)]
+            fn encode_field(
+                &self,
+                out: &mut ::solution::__private::Vec<u8>,
+            ) -> ::core::result::Result<(), ::solution::PacketError> {
+                #encode
+            }
+
+            #[allow(unused_variables)]
+            fn decode_field(
+                input: &mut &[u8],
+            ) -> ::core::result::Result<Self, ::solution::PacketError> {
+                #decode
+            }
+        }
+
+        impl #impl_generics ::solution::Packetable for #name #type_generics #where_clause {
+            fn try_to_packet_data_with(
+                &self,
+                builder: &::solution::PacketBuilder,
+            ) -> ::core::result::Result<::solution::__private::Vec<u8>, ::solution::PacketError> {
+                ::solution::encoding::encode_message(self, builder)
+            }
+
+            fn from_packet_data(
+                packet_data: &[u8],
+            ) -> ::core::result::Result<Self, ::solution::PacketError> {
+                ::solution::encoding::decode_message(packet_data)
+            }
+
+            fn from_packet_data_with_limit(
+                packet_data: &[u8],
+                limit: usize,
+            ) -> ::core::result::Result<Self, ::solution::PacketError> {
+                ::solution::encoding::decode_message_with_limit(packet_data, limit)
+            }
+        }
+    })
+}
+
+fn expand_struct(name: &Ident, fields: &Fields) -> (TokenStream2, TokenStream2) {
+    let accessors: Vec<TokenStream2> = match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|field| {
+                let ident = &field.ident;
+                quote! { #ident }
+            })
+            .collect(),
+        Fields::Unnamed(unnamed) => (0..unnamed.unnamed.len())
+            .map(|index| {
+                let index = Index::from(index);
+                quote! { #index }
+            })
+            .collect(),
+        Fields::Unit => Vec::new(),
+    };
+
+    let field_names = field_names(&name.to_string(), fields);
+    let constructor = construct(quote! { #name }, fields, &field_names);
+
+    let encode = quote! {
+        #( ::solution::PacketField::encode_field(&self.#accessors, out)?; )*
+        Ok(())
+    };
+    let decode = quote! {
+        Ok(#constructor)
+    };
+
+    (encode, decode)
+}
+
+fn field_bindings(fields: &Fields) -> Vec<Ident> {
+    match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|field| field.ident.clone().unwrap())
+            .collect(),
+        Fields::Unnamed(unnamed) => (0..unnamed.unnamed.len())
+            .map(|index| format_ident!("field_{}", index))
+            .collect(),
+        Fields::Unit => Vec::new(),
+    }
+}
+
+fn field_names(path: &str, fields: &Fields) -> Vec<String> {
+    match fields {
+        Fields::Named(named) => named
+            .named
+            .iter()
+            .map(|field| format!("{}.{}", path, field.ident.as_ref().unwrap()))
+            .collect(),
+        Fields::Unnamed(unnamed) => (0..unnamed.unnamed.len())
+            .map(|index| format!("{}.{}", path, index))
+            .collect(),
+        Fields::Unit => Vec::new(),
+    }
+}
+
+fn construct(path: TokenStream2, fields: &Fields, field_names: &[String]) -> TokenStream2 {
+    let decoders = field_names.iter().map(|field_name| {
+        quote! { ::solution::encoding::decode_named(input, #field_name)? }
+    });
+
+    match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            quote! { #path { #( #idents: #decoders ),* } }
+        }
+        Fields::Unnamed(_) => quote! { #path ( #(#decoders),* ) },
+        Fields::Unit => quote! { #path },
+    }
+}
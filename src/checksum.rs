@@ -0,0 +1,193 @@
+use crate::{PacketError, PacketErrorKind};
+
+pub trait Checksum {
+    fn checksum(&self, data: &[u8]) -> [u8; 4];
+}
+
+/// Sum of all bytes, the checksum used by protocol version 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Additive;
+
+impl Checksum for Additive {
+    fn checksum(&self, data: &[u8]) -> [u8; 4] {
+        additive(data).to_be_bytes()
+    }
+}
+
+/// CRC-32 with the IEEE 802.3 polynomial.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Crc32;
+
+impl Checksum for Crc32 {
+    fn checksum(&self, data: &[u8]) -> [u8; 4] {
+        crc32(&CRC32_TABLE, data).to_be_bytes()
+    }
+}
+
+/// CRC-32 with the Castagnoli polynomial.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Crc32c;
+
+impl Checksum for Crc32c {
+    fn checksum(&self, data: &[u8]) -> [u8; 4] {
+        crc32(&CRC32C_TABLE, data).to_be_bytes()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Adler32;
+
+impl Checksum for Adler32 {
+    fn checksum(&self, data: &[u8]) -> [u8; 4] {
+        adler32(data).to_be_bytes()
+    }
+}
+
+/// The checksum algorithms that can be identified on the wire.
+///
+/// The discriminant is stored in the low bits of the version 2 flags byte.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Additive = 0,
+    Crc32 = 1,
+    Crc32c = 2,
+    Adler32 = 3,
+}
+
+impl ChecksumAlgorithm {
+    pub fn from_id(id: u8) -> Result<Self, PacketError> {
+        match id {
+            0 => Ok(Self::Additive),
+            1 => Ok(Self::Crc32),
+            2 => Ok(Self::Crc32c),
+            3 => Ok(Self::Adler32),
+            _ => Err(PacketErrorKind::UnknownChecksumAlgorithm(id).into()),
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// The checksum of `parts` joined together, without copying them into one buffer.
+    pub(crate) fn checksum_parts(self, parts: &[&[u8]]) -> [u8; 4] {
+        let bytes = parts.iter().flat_map(|part| part.iter());
+        let checksum = match self {
+            Self::Additive => additive(bytes),
+            Self::Crc32 => crc32(&CRC32_TABLE, bytes),
+            Self::Crc32c => crc32(&CRC32C_TABLE, bytes),
+            Self::Adler32 => adler32(bytes),
+        };
+
+        checksum.to_be_bytes()
+    }
+}
+
+/// Length HMAC-SHA256 tags are truncated to when they replace the checksum, see
+/// `PacketBuilder::mac`.
+///
+/// Shares the low bits of the version 2 flags byte with [`ChecksumAlgorithm`], as ids 4 to 7.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MacLength {
+    Bytes4 = 4,
+    Bytes8 = 5,
+    Bytes16 = 6,
+    Bytes32 = 7,
+}
+
+impl MacLength {
+    /// Returns `None` for the ids of [`ChecksumAlgorithm`].
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            4 => Some(Self::Bytes4),
+            5 => Some(Self::Bytes8),
+            6 => Some(Self::Bytes16),
+            7 => Some(Self::Bytes32),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn tag_length(self) -> usize {
+        match self {
+            Self::Bytes4 => 4,
+            Self::Bytes8 => 8,
+            Self::Bytes16 => 16,
+            Self::Bytes32 => 32,
+        }
+    }
+}
+
+impl Checksum for ChecksumAlgorithm {
+    fn checksum(&self, data: &[u8]) -> [u8; 4] {
+        match self {
+            Self::Additive => Additive.checksum(data),
+            Self::Crc32 => Crc32.checksum(data),
+            Self::Crc32c => Crc32c.checksum(data),
+            Self::Adler32 => Adler32.checksum(data),
+        }
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table(0xEDB8_8320);
+const CRC32C_TABLE: [u32; 256] = crc32_table(0x82F6_3B78);
+
+const fn crc32_table(polynomial: u32) -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ polynomial
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+}
+
+fn additive<'a>(data: impl IntoIterator<Item = &'a u8>) -> u32 {
+    data.into_iter().map(|&byte| byte as u32).sum()
+}
+
+fn crc32<'a>(table: &[u32; 256], data: impl IntoIterator<Item = &'a u8>) -> u32 {
+    let crc = data.into_iter().fold(u32::MAX, |crc, &byte| {
+        table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    });
+
+    !crc
+}
+
+fn adler32<'a>(data: impl IntoIterator<Item = &'a u8>) -> u32 {
+    const MODULO: u32 = 65521;
+    // The largest block for which `b` cannot overflow.
+    const BLOCK: usize = 5552;
+
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for (index, &byte) in data.into_iter().enumerate() {
+        a += byte as u32;
+        b += a;
+        if index % BLOCK == BLOCK - 1 {
+            a %= MODULO;
+            b %= MODULO;
+        }
+    }
+    a %= MODULO;
+    b %= MODULO;
+
+    (b << 16) | a
+}
@@ -0,0 +1,221 @@
+use std::io;
+use std::marker::PhantomData;
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::reassembly::Slots;
+use crate::{
+    Decoded, Packet, PacketBuf, PacketBuilder, PacketError, PacketErrorKind, Packetable,
+    DEFAULT_DECOMPRESSION_LIMIT,
+};
+
+/// Frames single packets, for use with `tokio_util::codec::Framed` and friends.
+///
+/// Invalid packets are reported as `io::ErrorKind::InvalidData` errors wrapping the
+/// [`PacketError`], located from the first byte decoded, as with
+/// [`PacketReader`](crate::PacketReader). A stream that ends in the middle of a packet is
+/// reported as [`PacketErrorKind::InvalidPacket`].
+#[derive(Debug, Default)]
+pub struct PacketCodec {
+    consumed: usize,
+    decoded: usize,
+}
+
+impl PacketCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn locate(&self, error: PacketError) -> io::Error {
+        error.at(self.consumed, self.decoded).into()
+    }
+}
+
+impl Decoder for PacketCodec {
+    type Item = PacketBuf;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<PacketBuf>> {
+        let (packet, length) = match Packet::decode(src) {
+            Ok(Decoded::Packet(packet, remainder)) => {
+                (packet.to_packet_buf(), src.len() - remainder.len())
+            }
+            Ok(Decoded::Incomplete(needed)) => {
+                src.reserve(needed);
+                return Ok(None);
+            }
+            Err(error) => return Err(self.locate(error)),
+        };
+
+        src.advance(length);
+        self.consumed += length;
+        self.decoded += 1;
+
+        Ok(Some(packet))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<PacketBuf>> {
+        if let Some(packet) = self.decode(src)? {
+            return Ok(Some(packet));
+        }
+
+        // Whatever is left is part of a packet, which `deserialize` reports as cut short.
+        match Packet::deserialize(src) {
+            Err(error) if !src.is_empty() => Err(self.locate(error)),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Encoder<Packet<'_>> for PacketCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, packet: Packet<'_>, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(packet.encoded_len());
+        packet.write_to(dst);
+
+        Ok(())
+    }
+}
+
+impl Encoder<PacketBuf> for PacketCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, packet: PacketBuf, dst: &mut BytesMut) -> io::Result<()> {
+        dst.extend_from_slice(packet.as_bytes());
+
+        Ok(())
+    }
+}
+
+/// The default of [`MessageCodec::max_message_length`], as for
+/// `tokio_util::codec::LengthDelimitedCodec`.
+pub const DEFAULT_MAX_MESSAGE_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Frames whole [`Packetable`] messages, split into packets with the given builder.
+///
+/// A message ends with a packet marked as last or, for sequenced packets, once all of its
+/// packets have arrived, as with [`Packetable::next_message`]. The builder should therefore
+/// be [framed](PacketBuilder::framed) or [sequenced](PacketBuilder::sequenced), and packets
+/// of different messages must not be interleaved. Parity packets are skipped, as damaged
+/// packets already fail the stream. Packets left over at the end of the stream
+/// are reported as [`PacketErrorKind::IncompleteMessage`].
+#[derive(Debug)]
+pub struct MessageCodec<T> {
+    builder: PacketBuilder,
+    packets: PacketCodec,
+    message: Vec<u8>,
+    // Only tracks which packets have arrived, as the packets themselves are kept in `message`.
+    // Messages are not interleaved, so unlike `Reassembler` it need not tell late duplicates
+    // from a new message reusing their id.
+    slots: Slots<()>,
+    limit: usize,
+    max_length: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Packetable> MessageCodec<T> {
+    pub fn new(builder: PacketBuilder) -> Self {
+        MessageCodec {
+            builder,
+            packets: PacketCodec::new(),
+            message: Vec::new(),
+            slots: Slots::default(),
+            limit: DEFAULT_DECOMPRESSION_LIMIT,
+            max_length: DEFAULT_MAX_MESSAGE_LENGTH,
+            marker: PhantomData,
+        }
+    }
+
+    /// Sets the decompressed size above which messages are rejected, see
+    /// [`Packetable::from_packet_data_with_limit`].
+    pub fn decompression_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets the number of bytes of packets above which a message is rejected with
+    /// [`PacketErrorKind::MessageTooLarge`] instead of buffered further, which defaults to
+    /// [`DEFAULT_MAX_MESSAGE_LENGTH`].
+    pub fn max_message_length(mut self, length: usize) -> Self {
+        self.max_length = length;
+        self
+    }
+
+    fn finish(&mut self) -> io::Result<T> {
+        let message = std::mem::take(&mut self.message);
+
+        Ok(T::from_packet_data_with_limit(&message, self.limit)?)
+    }
+}
+
+impl<T: Packetable> Decoder for MessageCodec<T> {
+    type Item = T;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<T>> {
+        loop {
+            let (offset, index) = (self.packets.consumed, self.packets.decoded);
+            let packet = match self.packets.decode(src)? {
+                Some(packet) => packet,
+                None => return Ok(None),
+            };
+            if packet.packet().is_parity() {
+                continue;
+            }
+
+            // Checked first, so that a rejected packet is not counted towards its message.
+            if self.message.len() + packet.as_bytes().len() > self.max_length {
+                self.message = Vec::new();
+                self.slots = Slots::default();
+                let error = PacketErrorKind::MessageTooLarge {
+                    limit: self.max_length,
+                };
+                return Err(PacketError::from(error).at(offset, index).into());
+            }
+            let complete = match packet.packet().sequence() {
+                Some(sequence) => self
+                    .slots
+                    .push(sequence, ())
+                    .map_err(|error| error.at(offset, index))?
+                    .is_some(),
+                None => packet.packet().is_last(),
+            };
+            self.message.extend_from_slice(packet.as_bytes());
+
+            if complete {
+                return self.finish().map(Some);
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<T>> {
+        if let Some(message) = self.decode(src)? {
+            return Ok(Some(message));
+        }
+        self.packets.decode_eof(src)?;
+
+        if self.message.is_empty() {
+            return Ok(None);
+        }
+        let error = PacketError::from(PacketErrorKind::IncompleteMessage);
+        Err(self.packets.locate(error))
+    }
+}
+
+impl<T: Packetable> Encoder<T> for MessageCodec<T> {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: T, dst: &mut BytesMut) -> io::Result<()> {
+        self.encode(&message, dst)
+    }
+}
+
+impl<T: Packetable> Encoder<&T> for MessageCodec<T> {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: &T, dst: &mut BytesMut) -> io::Result<()> {
+        Ok(message.write_packet_data_with(&self.builder, dst)?)
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{PacketError, PacketErrorKind};
+
+/// Decompressed size above which [`Packetable::from_packet_data`] rejects a compressed
+/// message, see [`Packetable::from_packet_data_with_limit`].
+///
+/// [`Packetable::from_packet_data`]: crate::Packetable::from_packet_data
+/// [`Packetable::from_packet_data_with_limit`]: crate::Packetable::from_packet_data_with_limit
+#[cfg(feature = "alloc")]
+pub const DEFAULT_DECOMPRESSION_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Codec used to compress a whole message before it is split into packets.
+///
+/// The discriminant is stored in the first byte of the compressed message. Each codec is
+/// only available with the cargo feature of the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Raw DEFLATE, without zlib or gzip framing.
+    Deflate = 1,
+    /// An LZ4 block, prefixed with its decompressed size as a little endian `u32`.
+    Lz4 = 2,
+}
+
+impl Compression {
+    pub fn from_id(id: u8) -> Result<Self, PacketError> {
+        match id {
+            1 => Ok(Self::Deflate),
+            2 => Ok(Self::Lz4),
+            _ => Err(PacketErrorKind::UnknownCompression(id).into()),
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether the codec was compiled in.
+    pub fn is_enabled(self) -> bool {
+        match self {
+            Self::Deflate => cfg!(feature = "deflate"),
+            Self::Lz4 => cfg!(feature = "lz4"),
+        }
+    }
+
+    /// Compresses `message` and puts the codec id in front of it.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(
+        not(any(feature = "deflate", feature = "lz4")),
+        allow(unused_variables)
+    )]
+    pub(crate) fn compress(self, message: &[u8]) -> Result<Vec<u8>, PacketError> {
+        let data: Option<Vec<u8>> = match self {
+            #[cfg(feature = "deflate")]
+            Self::Deflate => Some(miniz_oxide::deflate::compress_to_vec(message, 6)),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Some(lz4_flex::block::compress_prepend_size(message)),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+        let data = data.ok_or(PacketErrorKind::UnknownCompression(self.id()))?;
+
+        let mut compressed = Vec::with_capacity(data.len() + 1);
+        compressed.push(self.id());
+        compressed.extend(data);
+
+        Ok(compressed)
+    }
+}
+
+/// Reverses [`Compression::compress`], failing with [`PacketErrorKind::MessageTooLarge`]
+/// rather than producing more than `limit` bytes.
+#[cfg(feature = "alloc")]
+#[cfg_attr(
+    not(any(feature = "deflate", feature = "lz4")),
+    allow(unused_variables)
+)]
+pub(crate) fn decompress(compressed: &[u8], limit: usize) -> Result<Vec<u8>, PacketError> {
+    let (&id, data) = compressed
+        .split_first()
+        .ok_or(PacketErrorKind::CorruptedMessage)?;
+    let too_large = || PacketError::from(PacketErrorKind::MessageTooLarge { limit });
+
+    match Compression::from_id(id)? {
+        #[cfg(feature = "deflate")]
+        Compression::Deflate => miniz_oxide::inflate::decompress_to_vec_with_limit(data, limit)
+            .map_err(|error| match error.status {
+                miniz_oxide::inflate::TINFLStatus::HasMoreOutput => too_large(),
+                _ => PacketErrorKind::CorruptedMessage.into(),
+            }),
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => {
+            let (size, block) = lz4_flex::block::uncompressed_size(data)
+                .map_err(|_| PacketError::from(PacketErrorKind::CorruptedMessage))?;
+            if size > limit {
+                return Err(too_large());
+            }
+
+            match lz4_flex::block::decompress(block, size) {
+                Ok(message) if message.len() == size => Ok(message),
+                _ => Err(PacketErrorKind::CorruptedMessage.into()),
+            }
+        }
+        #[allow(unreachable_patterns)]
+        _ => Err(PacketErrorKind::UnknownCompression(id).into()),
+    }
+}
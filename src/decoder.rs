@@ -0,0 +1,202 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::Skipped;
+use crate::{Header, Packet, PacketError};
+
+/// Outcome of decoding the start of a buffer that may hold only part of a packet.
+#[derive(Debug, PartialEq)]
+pub enum Decoded<'a> {
+    Packet(Packet<'a>, &'a [u8]),
+    /// The packet is cut short; holds the least number of additional bytes it needs.
+    Incomplete(usize),
+}
+
+impl<'a> Packet<'a> {
+    /// Like [`Packet::deserialize`], but a packet cut short by the end of `bytes` is reported as
+    /// [`Decoded::Incomplete`] instead of an
+    /// [`InvalidPacket`](crate::PacketErrorKind::InvalidPacket) error.
+    pub fn decode(bytes: &'a [u8]) -> Result<Decoded<'a>, PacketError> {
+        let header = match Header::parse(bytes)? {
+            Some(header) => header,
+            None => {
+                return Ok(Decoded::Incomplete(
+                    Header::minimum_length(bytes)? - bytes.len(),
+                ))
+            }
+        };
+
+        if header.packet_length() > bytes.len() {
+            return Ok(Decoded::Incomplete(header.packet_length() - bytes.len()));
+        }
+
+        Packet::deserialize(bytes).map(|(packet, remainder)| Decoded::Packet(packet, remainder))
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn to_packet_buf(&self) -> PacketBuf {
+        PacketBuf {
+            bytes: self.serialize(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// An owned, already validated packet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PacketBuf {
+    bytes: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl PacketBuf {
+    pub fn packet(&self) -> Packet<'_> {
+        let header = Header::parse(&self.bytes).unwrap().unwrap();
+        Packet::split(&header, &self.bytes).0
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        self.packet().payload()
+    }
+
+    /// The packet in its serialized form.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// Decodes packets from data that arrives in arbitrary chunks.
+#[derive(Debug, Default)]
+pub struct PacketDecoder {
+    buffer: Vec<u8>,
+    start: usize,
+    consumed: usize,
+    decoded: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl PacketDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `chunk` to the buffered data and returns the packets that are now complete.
+    ///
+    /// The iterator stops when the buffer holds only part of a packet, or after yielding an
+    /// error for malformed input. Malformed bytes stay buffered until [`PacketDecoder::clear`]
+    /// is called, so feeding more data will report the same error again.
+    pub fn feed(&mut self, chunk: &[u8]) -> Packets<'_> {
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        self.buffer.extend_from_slice(chunk);
+
+        Packets {
+            decoder: self,
+            failed: false,
+        }
+    }
+
+    /// The least number of bytes that must be fed before another packet can be completed.
+    ///
+    /// Returns 0 when a complete packet or malformed input is already buffered.
+    pub fn needed(&self) -> usize {
+        match Packet::decode(self.buffered()) {
+            Ok(Decoded::Incomplete(needed)) => needed,
+            _ => 0,
+        }
+    }
+
+    /// The bytes that have been fed but not yet decoded.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer[self.start..]
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.start = 0;
+        self.consumed = 0;
+        self.decoded = 0;
+    }
+
+    /// Drops malformed bytes from the front of the buffer, up to the next offset that could
+    /// start a packet, and reports what was dropped.
+    ///
+    /// The next complete, valid packet is preferred. Only if none is buffered does it stop at
+    /// the first offset that could start a packet still being received, as almost any byte
+    /// following a version byte looks like the start of one.
+    ///
+    /// Returns `None` if the buffer does not start with malformed input. Ranges, like the
+    /// locations of errors, are offsets into everything fed to the decoder since it was
+    /// created or cleared.
+    pub fn resync(&mut self) -> Option<Skipped> {
+        let buffered = self.buffered();
+        let error = Packet::decode(buffered)
+            .err()?
+            .at(self.consumed, self.decoded);
+
+        let mut incomplete = None;
+        let skip = (1..buffered.len())
+            .find(|&offset| match Packet::decode(&buffered[offset..]) {
+                Ok(Decoded::Packet(..)) => true,
+                Ok(Decoded::Incomplete(_)) => {
+                    incomplete.get_or_insert(offset);
+                    false
+                }
+                Err(_) => false,
+            })
+            .or(incomplete)
+            .unwrap_or(buffered.len());
+        let start = self.consumed;
+        self.advance(skip);
+
+        Some(Skipped {
+            range: start..(start + skip),
+            error,
+        })
+    }
+
+    fn advance(&mut self, count: usize) {
+        self.start += count;
+        self.consumed += count;
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct Packets<'a> {
+    decoder: &'a mut PacketDecoder,
+    failed: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> Iterator for Packets<'a> {
+    type Item = Result<PacketBuf, PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let buffered = self.decoder.buffered();
+        match Packet::decode(buffered) {
+            Ok(Decoded::Packet(packet, remainder)) => {
+                let packet = packet.to_packet_buf();
+                let length = buffered.len() - remainder.len();
+                self.decoder.advance(length);
+                self.decoder.decoded += 1;
+                Some(Ok(packet))
+            }
+            Ok(Decoded::Incomplete(_)) => None,
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error.at(self.decoder.consumed, self.decoder.decoded)))
+            }
+        }
+    }
+}
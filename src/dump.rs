@@ -0,0 +1,151 @@
+use core::convert::TryInto;
+use core::fmt;
+
+use crate::{Packet, ProtocolVersion, Recovered, Resync};
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Formats bytes as lines of offset, hex and ASCII columns, non-printable bytes shown as `.`.
+///
+/// Every line is indented by two spaces and ends with a newline.
+#[derive(Clone, Copy, Debug)]
+pub struct HexDump<'a>(pub &'a [u8]);
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (line, bytes) in self.0.chunks(BYTES_PER_LINE).enumerate() {
+            write!(f, "  {:04x} ", line * BYTES_PER_LINE)?;
+            for index in 0..BYTES_PER_LINE {
+                if index % 8 == 0 {
+                    write!(f, " ")?;
+                }
+                match bytes.get(index) {
+                    Some(byte) => write!(f, "{:02x} ", byte)?,
+                    None => write!(f, "   ")?,
+                }
+            }
+
+            write!(f, " |")?;
+            for &byte in bytes {
+                let shown = if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                };
+                write!(f, "{}", shown)?;
+            }
+            writeln!(f, "|")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Shows the header fields, the payload as a [`HexDump`] and the checksum, marked with `✓`
+/// if it matches the payload and `✗` otherwise. MACs are shown without being verified.
+impl fmt::Display for Packet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Packet v{}, {} bytes", self.version, self.payload.len())?;
+        if self.version != ProtocolVersion::V1 as u8 {
+            match self.checksum_algorithm() {
+                Some(algorithm) => write!(f, ", {:?}", algorithm)?,
+                None => write!(f, ", HMAC")?,
+            }
+        }
+        if let Some(sequence) = self.sequence {
+            write!(
+                f,
+                ", sequence {}:{}/{}",
+                sequence.message_id, sequence.index, sequence.count
+            )?;
+        }
+        if self.is_compressed() {
+            write!(f, ", compressed")?;
+        }
+        if self.is_encrypted() {
+            write!(f, ", encrypted")?;
+        }
+        if self.is_parity() {
+            write!(f, ", parity")?;
+        }
+        if self.is_last() {
+            write!(f, ", last")?;
+        }
+        writeln!(f)?;
+
+        if self.payload.is_empty() {
+            writeln!(f, "  (empty)")?;
+        }
+        write!(f, "{}", HexDump(self.payload))?;
+
+        let algorithm = match self.checksum_algorithm() {
+            Some(algorithm) => algorithm,
+            None => {
+                write!(f, "  mac ")?;
+                for byte in self.checksum() {
+                    write!(f, "{:02x}", byte)?;
+                }
+                return write!(f, " (not verified)");
+            }
+        };
+        let checksum: [u8; 4] = self.checksum().try_into().unwrap();
+        let computed = self.compute_checksum(algorithm);
+        write!(f, "  checksum {:08x} ", u32::from_be_bytes(checksum))?;
+        if computed == checksum {
+            write!(f, "✓")
+        } else {
+            write!(f, "✗ (computed {:08x})", u32::from_be_bytes(computed))
+        }
+    }
+}
+
+/// Annotated dump of a whole packet stream.
+///
+/// Every packet is shown with its offset, index and [`Packet`]'s `Display` output. Packets
+/// with a wrong checksum are still shown; bytes that cannot be parsed as a packet are
+/// reported with the error and dumped up to the next valid packet.
+#[derive(Clone, Copy, Debug)]
+pub struct StreamDump<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StreamDump<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        StreamDump { bytes }
+    }
+}
+
+impl fmt::Display for StreamDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut offset = 0;
+        let mut index = 0;
+
+        while offset < self.bytes.len() {
+            let remaining = &self.bytes[offset..];
+            match Packet::deserialize_unverified(remaining) {
+                Ok((packet, _)) => {
+                    writeln!(f, "#{} at byte {}: {}", index, offset, packet)?;
+                    offset += packet.encoded_len();
+                    index += 1;
+                }
+                Err(error) => {
+                    let length = match Resync::new(remaining).next() {
+                        Some(Recovered::Skipped(skipped)) => skipped.range.end,
+                        _ => remaining.len(),
+                    };
+                    writeln!(
+                        f,
+                        "bytes {}..{} skipped: {}",
+                        offset,
+                        offset + length,
+                        error
+                    )?;
+                    write!(f, "{}", HexDump(&remaining[..length]))?;
+                    offset += length;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+//! Deterministic byte layout used by `#[derive(Packetable)]`.
+//!
+//! Integers and floats are big endian, `bool` is a single `0`/`1` byte, `char` is its
+//! scalar value as a `u32`, and strings and vectors are prefixed with their length as a
+//! `u32`. `Option` is a `0`/`1` tag followed by the value. Struct fields are written in
+//! declaration order and enums write the variant index as a `u32` before its fields.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use crate::{PacketBuilder, PacketError, PacketErrorKind, ToPackets};
+
+pub trait PacketField: Sized {
+    fn encode_field(&self, out: &mut Vec<u8>) -> Result<(), PacketError>;
+    fn decode_field(input: &mut &[u8]) -> Result<Self, PacketError>;
+}
+
+/// Decodes a single field, reporting failures as [`PacketErrorKind::InvalidField`].
+///
+/// Errors that already name a field are kept as they are, so the innermost field is reported.
+pub fn decode_named<T: PacketField>(
+    input: &mut &[u8],
+    field: &'static str,
+) -> Result<T, PacketError> {
+    T::decode_field(input).map_err(|error| match error.kind() {
+        PacketErrorKind::InvalidField(_) => error,
+        _ => PacketErrorKind::InvalidField(field).into(),
+    })
+}
+
+pub fn encode_message<T: PacketField>(
+    value: &T,
+    builder: &PacketBuilder,
+) -> Result<Vec<u8>, PacketError> {
+    let mut bytes = Vec::new();
+    value.encode_field(&mut bytes)?;
+
+    bytes.try_to_packet_data_with(builder)
+}
+
+pub fn decode_message<T: PacketField>(packet_data: &[u8]) -> Result<T, PacketError> {
+    decode_message_with_limit(packet_data, crate::DEFAULT_DECOMPRESSION_LIMIT)
+}
+
+pub fn decode_message_with_limit<T: PacketField>(
+    packet_data: &[u8],
+    limit: usize,
+) -> Result<T, PacketError> {
+    let bytes: Vec<u8> = crate::Packetable::from_packet_data_with_limit(packet_data, limit)?;
+    let mut input = &bytes[..];
+
+    let value = T::decode_field(&mut input)?;
+    if !input.is_empty() {
+        return Err(PacketErrorKind::CorruptedMessage.into());
+    }
+
+    Ok(value)
+}
+
+fn take<'a>(input: &mut &'a [u8], count: usize) -> Result<&'a [u8], PacketError> {
+    if input.len() < count {
+        return Err(PacketErrorKind::CorruptedMessage.into());
+    }
+
+    let (taken, rest) = input.split_at(count);
+    *input = rest;
+
+    Ok(taken)
+}
+
+fn encode_length(length: usize, out: &mut Vec<u8>) -> Result<(), PacketError> {
+    let length: u32 = length
+        .try_into()
+        .map_err(|_| PacketError::from(PacketErrorKind::MessageTooLong))?;
+    length.encode_field(out)
+}
+
+macro_rules! impl_number_field {
+    ($($number:ty),*) => {
+        $(
+            impl PacketField for $number {
+                fn encode_field(&self, out: &mut Vec<u8>) -> Result<(), PacketError> {
+                    out.extend_from_slice(&self.to_be_bytes());
+                    Ok(())
+                }
+
+                fn decode_field(input: &mut &[u8]) -> Result<Self, PacketError> {
+                    let bytes = take(input, core::mem::size_of::<$number>())?;
+                    Ok(<$number>::from_be_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_number_field!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl PacketField for bool {
+    fn encode_field(&self, out: &mut Vec<u8>) -> Result<(), PacketError> {
+        out.push(*self as u8);
+        Ok(())
+    }
+
+    fn decode_field(input: &mut &[u8]) -> Result<Self, PacketError> {
+        match u8::decode_field(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(PacketErrorKind::CorruptedMessage.into()),
+        }
+    }
+}
+
+impl PacketField for char {
+    fn encode_field(&self, out: &mut Vec<u8>) -> Result<(), PacketError> {
+        (*self as u32).encode_field(out)
+    }
+
+    fn decode_field(input: &mut &[u8]) -> Result<Self, PacketError> {
+        core::char::from_u32(u32::decode_field(input)?)
+            .ok_or_else(|| PacketErrorKind::CorruptedMessage.into())
+    }
+}
+
+impl PacketField for String {
+    fn encode_field(&self, out: &mut Vec<u8>) -> Result<(), PacketError> {
+        encode_length(self.len(), out)?;
+        out.extend_from_slice(self.as_bytes());
+        Ok(())
+    }
+
+    fn decode_field(input: &mut &[u8]) -> Result<Self, PacketError> {
+        let length = u32::decode_field(input)? as usize;
+        let bytes = take(input, length)?;
+
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+}
+
+impl<T: PacketField> PacketField for Vec<T> {
+    fn encode_field(&self, out: &mut Vec<u8>) -> Result<(), PacketError> {
+        encode_length(self.len(), out)?;
+        for item in self {
+            item.encode_field(out)?;
+        }
+        Ok(())
+    }
+
+    fn decode_field(input: &mut &[u8]) -> Result<Self, PacketError> {
+        let length = u32::decode_field(input)? as usize;
+        // The length comes from the wire, so don't trust it for the allocation.
+        let mut items = Vec::with_capacity(length.min(input.len()));
+        for _ in 0..length {
+            items.push(T::decode_field(input)?);
+        }
+
+        Ok(items)
+    }
+}
+
+impl<T: PacketField> PacketField for Option<T> {
+    fn encode_field(&self, out: &mut Vec<u8>) -> Result<(), PacketError> {
+        match self {
+            Some(value) => {
+                true.encode_field(out)?;
+                value.encode_field(out)
+            }
+            None => false.encode_field(out),
+        }
+    }
+
+    fn decode_field(input: &mut &[u8]) -> Result<Self, PacketError> {
+        if bool::decode_field(input)? {
+            T::decode_field(input).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: PacketField> PacketField for Box<T> {
+    fn encode_field(&self, out: &mut Vec<u8>) -> Result<(), PacketError> {
+        (**self).encode_field(out)
+    }
+
+    fn decode_field(input: &mut &[u8]) -> Result<Self, PacketError> {
+        T::decode_field(input).map(Box::new)
+    }
+}
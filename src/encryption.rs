@@ -0,0 +1,243 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::fmt;
+
+use aes_gcm::Aes256Gcm;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+
+use crate::{
+    checksum_field, Packet, PacketError, PacketErrorKind, PacketIter, ProtocolVersion,
+    ENCRYPTED_FLAG,
+};
+
+const COUNTER_LENGTH: usize = 8;
+const TAG_LENGTH: usize = 16;
+
+/// Bytes that sealing adds to the payload of every packet.
+pub const SEAL_OVERHEAD: usize = COUNTER_LENGTH + TAG_LENGTH;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CipherAlgorithm {
+    ChaCha20Poly1305,
+    Aes256Gcm,
+}
+
+enum Cipher {
+    ChaCha20Poly1305(ChaCha20Poly1305),
+    // The expanded AES key schedule is much larger than the ChaCha20 key.
+    Aes256Gcm(Box<Aes256Gcm>),
+}
+
+/// Seals and opens packet data with an AEAD cipher, for use over untrusted links.
+///
+/// Every packet is encrypted on its own, with a nonce built from a 64-bit counter that goes
+/// up with every packet sealed. The counter is sent in front of the ciphertext, so lost or
+/// reordered packets do not stop the others from being opened. The packet header is
+/// authenticated along with the payload, so its flags and sequence cannot be changed either.
+///
+/// A key must never seal two packets with the same counter. Use a fresh key for every
+/// session, or carry on from [`PacketCipher::counter`] with [`PacketCipher::with_counter`].
+/// Once the counter reaches `u64::MAX`, sealing fails with
+/// [`PacketErrorKind::CounterExhausted`]. Opening does not detect replayed packets.
+pub struct PacketCipher {
+    algorithm: CipherAlgorithm,
+    cipher: Cipher,
+    counter: u64,
+}
+
+impl PacketCipher {
+    pub fn new(algorithm: CipherAlgorithm, key: &[u8; 32]) -> Self {
+        let cipher = match algorithm {
+            CipherAlgorithm::ChaCha20Poly1305 => {
+                Cipher::ChaCha20Poly1305(ChaCha20Poly1305::new(key.into()))
+            }
+            CipherAlgorithm::Aes256Gcm => Cipher::Aes256Gcm(Box::new(Aes256Gcm::new(key.into()))),
+        };
+
+        PacketCipher {
+            algorithm,
+            cipher,
+            counter: 0,
+        }
+    }
+
+    /// Starts sealing at `counter` instead of 0.
+    pub fn with_counter(mut self, counter: u64) -> Self {
+        self.counter = counter;
+        self
+    }
+
+    pub fn algorithm(&self) -> CipherAlgorithm {
+        self.algorithm
+    }
+
+    /// The counter the next sealed packet will use.
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// Encrypts every packet of `packet_data`, which must be valid version 2 packets.
+    ///
+    /// The payloads grow by [`SEAL_OVERHEAD`] bytes, so packets already close to the
+    /// maximum size are rejected with [`PacketErrorKind::InvalidPacketSize`].
+    pub fn seal(&mut self, packet_data: &[u8]) -> Result<Vec<u8>, PacketError> {
+        let mut sealed = Vec::with_capacity(packet_data.len());
+        let mut packets = PacketIter::new(packet_data);
+        let mut index = 0;
+
+        loop {
+            let offset = packet_data.len() - packets.remainder().len();
+            let packet = match packets.next() {
+                Some(packet) => packet?,
+                None => break,
+            };
+            self.seal_packet(&packet, &mut sealed)
+                .map_err(|error| error.at(offset, index))?;
+            index += 1;
+        }
+
+        Ok(sealed)
+    }
+
+    fn seal_packet(&mut self, packet: &Packet, out: &mut Vec<u8>) -> Result<(), PacketError> {
+        if packet.version == ProtocolVersion::V1 as u8 {
+            return Err(PacketErrorKind::UnsupportedByVersion.into());
+        }
+        // The tag of a packet carrying a MAC could not be recomputed without its key.
+        let algorithm = match packet.checksum_algorithm() {
+            Some(algorithm) if !packet.is_encrypted() => algorithm,
+            _ => return Err(PacketErrorKind::InvalidFlags(packet.flags).into()),
+        };
+        let size = (packet.payload.len() + SEAL_OVERHEAD)
+            .try_into()
+            .map_err(|_| PacketError::from(PacketErrorKind::InvalidPacketSize))?;
+
+        let mut sealed = Packet {
+            flags: packet.flags | ENCRYPTED_FLAG,
+            size,
+            payload: &[],
+            ..*packet
+        };
+        let (header, header_length) = sealed.header();
+        let counter = self.counter;
+        // Wrapping around would reuse nonces, which breaks both ciphers.
+        let next = counter
+            .checked_add(1)
+            .ok_or(PacketErrorKind::CounterExhausted)?;
+        let ciphertext = self
+            .cipher
+            .encrypt(counter, packet.payload, &header[..header_length]);
+        self.counter = next;
+
+        let mut payload = Vec::with_capacity(size as usize);
+        payload.extend_from_slice(&counter.to_be_bytes());
+        payload.extend(ciphertext);
+
+        sealed.payload = &payload;
+        sealed.checksum = checksum_field(sealed.compute_checksum(algorithm));
+        sealed.write_to(out);
+
+        Ok(())
+    }
+
+    /// Decrypts every packet of `packet_data`, returning packet data for
+    /// [`Packetable::from_packet_data`](crate::Packetable::from_packet_data) and friends.
+    ///
+    /// Fails with [`PacketErrorKind::AuthenticationFailed`] for packets that were not
+    /// sealed with this key, were tampered with, or were not sealed at all.
+    pub fn open(&self, packet_data: &[u8]) -> Result<Vec<u8>, PacketError> {
+        let mut opened = Vec::with_capacity(packet_data.len());
+        let mut packets = PacketIter::new(packet_data);
+        let mut index = 0;
+
+        loop {
+            let offset = packet_data.len() - packets.remainder().len();
+            let packet = match packets.next() {
+                Some(packet) => packet?,
+                None => break,
+            };
+            self.open_packet(&packet, &mut opened)
+                .map_err(|error| error.at(offset, index))?;
+            index += 1;
+        }
+
+        Ok(opened)
+    }
+
+    fn open_packet(&self, packet: &Packet, out: &mut Vec<u8>) -> Result<(), PacketError> {
+        let authentication_failed = || PacketError::from(PacketErrorKind::AuthenticationFailed);
+        let algorithm = match packet.checksum_algorithm() {
+            Some(algorithm) if packet.is_encrypted() && packet.payload.len() >= SEAL_OVERHEAD => {
+                algorithm
+            }
+            _ => return Err(authentication_failed()),
+        };
+
+        let (counter, ciphertext) = packet.payload.split_at(COUNTER_LENGTH);
+        let counter = u64::from_be_bytes(counter.try_into().unwrap());
+        let (header, header_length) = packet.header();
+        let plaintext = self
+            .cipher
+            .decrypt(counter, ciphertext, &header[..header_length])
+            .ok_or_else(authentication_failed)?;
+
+        let mut opened = Packet {
+            flags: packet.flags & !ENCRYPTED_FLAG,
+            size: plaintext.len() as u16,
+            payload: &plaintext,
+            ..*packet
+        };
+        opened.checksum = checksum_field(opened.compute_checksum(algorithm));
+        opened.write_to(out);
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PacketCipher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PacketCipher")
+            .field("algorithm", &self.algorithm)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cipher {
+    fn encrypt(&self, counter: u64, plaintext: &[u8], header: &[u8]) -> Vec<u8> {
+        let nonce = nonce(counter);
+        let payload = Payload {
+            msg: plaintext,
+            aad: header,
+        };
+
+        match self {
+            Self::ChaCha20Poly1305(cipher) => cipher.encrypt(&nonce.into(), payload),
+            Self::Aes256Gcm(cipher) => cipher.encrypt(&nonce.into(), payload),
+        }
+        .expect("payloads are far below the cipher's length limit")
+    }
+
+    fn decrypt(&self, counter: u64, ciphertext: &[u8], header: &[u8]) -> Option<Vec<u8>> {
+        let nonce = nonce(counter);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: header,
+        };
+
+        match self {
+            Self::ChaCha20Poly1305(cipher) => cipher.decrypt(&nonce.into(), payload),
+            Self::Aes256Gcm(cipher) => cipher.decrypt(&nonce.into(), payload),
+        }
+        .ok()
+    }
+}
+
+/// The 96-bit nonce for a counter, zero padded in front.
+fn nonce(counter: u64) -> [u8; 12] {
+    let mut nonce = [0; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
@@ -0,0 +1,211 @@
+#[cfg(feature = "alloc")]
+use alloc::string::FromUtf8Error;
+use core::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PacketErrorKind {
+    /// The packet needs more bytes than the input holds.
+    InvalidPacket {
+        declared: usize,
+        available: usize,
+    },
+    InvalidFlags(u8),
+    /// `expected` is the checksum carried by the packet, `computed` the one of its contents.
+    InvalidChecksum {
+        expected: [u8; 4],
+        computed: [u8; 4],
+    },
+    UnknownProtocolVersion(u8),
+    UnknownChecksumAlgorithm(u8),
+    /// The compression codec is unknown, or was not enabled when building the crate.
+    UnknownCompression(u8),
+    CorruptedMessage,
+    InvalidField(&'static str),
+    MissingSequence,
+    InvalidSequence,
+    DuplicatePacket,
+    IncompleteMessage,
+    InvalidPacketSize,
+    UnsupportedByVersion,
+    /// The builder option needs the whole message up front, which
+    /// [`PacketWriter`](crate::PacketWriter) does not have.
+    UnsupportedByWriter,
+    /// The message has too many packets to be sequenced, or holds a string or vector longer
+    /// than a `u32` length prefix allows.
+    MessageTooLong,
+    /// A compressed message would decompress to more than `limit` bytes, or a message being
+    /// received has grown beyond `limit` bytes of packets.
+    MessageTooLarge {
+        limit: usize,
+    },
+    /// The packet starts a message while too many others are waiting for packets, see
+    /// [`MAX_PENDING_MESSAGES`](crate::MAX_PENDING_MESSAGES).
+    TooManyPendingMessages,
+    /// The packet is compressed and can only be decoded along with the rest of its message,
+    /// see [`Packetable::from_packet_data`](crate::Packetable::from_packet_data).
+    CompressedPacket,
+    /// The packet is sequenced and its message has to be put back in order first, see
+    /// [`Reassembler`](crate::Reassembler).
+    SequencedPacket,
+    /// The packet is encrypted and has to be opened before its message can be decoded.
+    EncryptedPacket,
+    /// The packet was not sealed or tagged with the expected key, or was changed afterwards.
+    AuthenticationFailed,
+    /// The cipher has used up its counter and needs a new key, see `PacketCipher`.
+    CounterExhausted,
+    /// The packet carries a MAC, which can only be verified with the key, see `MacKey`.
+    MissingKey,
+    BufferTooSmall {
+        required: usize,
+        available: usize,
+    },
+}
+
+impl fmt::Display for PacketErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidPacket {
+                declared,
+                available,
+            } => write!(
+                f,
+                "Invalid packet: needs {} bytes, but only {} are available",
+                declared, available
+            ),
+            Self::InvalidFlags(flags) => write!(f, "Invalid packet flags {:#010b}", flags),
+            Self::InvalidChecksum { expected, computed } => write!(
+                f,
+                "Checksum invalid: expected {:08x}, computed {:08x}",
+                u32::from_be_bytes(*expected),
+                u32::from_be_bytes(*computed)
+            ),
+            Self::UnknownProtocolVersion(version) => {
+                write!(f, "Unknown protocol version {}", version)
+            }
+            Self::UnknownChecksumAlgorithm(id) => write!(f, "Unknown checksum algorithm {}", id),
+            Self::UnknownCompression(id) => write!(f, "Unknown compression codec {}", id),
+            Self::CorruptedMessage => write!(f, "Data is corrupted"),
+            Self::InvalidField(field) => write!(f, "Invalid value for field {}", field),
+            Self::MissingSequence => write!(f, "Packet has no sequence number"),
+            Self::InvalidSequence => write!(f, "Packet sequence is inconsistent"),
+            Self::DuplicatePacket => write!(f, "Packet was already received"),
+            Self::IncompleteMessage => write!(f, "Message is missing packets"),
+            Self::InvalidPacketSize => write!(f, "Packet size is out of range"),
+            Self::UnsupportedByVersion => {
+                write!(f, "Option is not supported by the protocol version")
+            }
+            Self::UnsupportedByWriter => {
+                write!(
+                    f,
+                    "Option is not supported when writing packets as a stream"
+                )
+            }
+            Self::MessageTooLong => write!(f, "Message is too long to be encoded"),
+            Self::MessageTooLarge { limit } => {
+                write!(f, "Message is larger than {} bytes", limit)
+            }
+            Self::TooManyPendingMessages => write!(f, "Too many messages are incomplete"),
+            Self::CompressedPacket => write!(f, "Packet is compressed"),
+            Self::SequencedPacket => write!(f, "Packet is sequenced"),
+            Self::EncryptedPacket => write!(f, "Packet is encrypted"),
+            Self::AuthenticationFailed => write!(f, "Packet authentication failed"),
+            Self::CounterExhausted => write!(f, "Cipher counter is exhausted"),
+            Self::MissingKey => write!(f, "Packet needs a key to be verified"),
+            Self::BufferTooSmall {
+                required,
+                available,
+            } => write!(
+                f,
+                "Buffer too small: needs {} bytes, but only {} are available",
+                required, available
+            ),
+        }
+    }
+}
+
+/// An error together with where in the input it was found, when that is known.
+///
+/// The offset is counted in bytes from the start of the input given to the decoding
+/// function, and the packet index is the number of packets that were decoded before it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PacketError {
+    kind: PacketErrorKind,
+    offset: Option<usize>,
+    packet_index: Option<usize>,
+    #[cfg(feature = "alloc")]
+    utf8_error: Option<FromUtf8Error>,
+}
+
+impl PacketError {
+    pub fn new(kind: PacketErrorKind) -> Self {
+        PacketError {
+            kind,
+            offset: None,
+            packet_index: None,
+            #[cfg(feature = "alloc")]
+            utf8_error: None,
+        }
+    }
+
+    pub fn kind(&self) -> &PacketErrorKind {
+        &self.kind
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    pub fn packet_index(&self) -> Option<usize> {
+        self.packet_index
+    }
+
+    /// The cause of a [`PacketErrorKind::CorruptedMessage`] raised for a message that is not
+    /// valid UTF-8. It also holds the bytes of the message.
+    #[cfg(feature = "alloc")]
+    pub fn utf8_error(&self) -> Option<&FromUtf8Error> {
+        self.utf8_error.as_ref()
+    }
+
+    /// Records where the error happened, unless a location is already known.
+    pub(crate) fn at(mut self, offset: usize, packet_index: usize) -> Self {
+        if self.offset.is_none() {
+            self.offset = Some(offset);
+            self.packet_index = Some(packet_index);
+        }
+        self
+    }
+}
+
+impl From<PacketErrorKind> for PacketError {
+    fn from(kind: PacketErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<FromUtf8Error> for PacketError {
+    fn from(error: FromUtf8Error) -> Self {
+        PacketError {
+            utf8_error: Some(error),
+            ..Self::new(PacketErrorKind::CorruptedMessage)
+        }
+    }
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let (Some(offset), Some(packet_index)) = (self.offset, self.packet_index) {
+            write!(f, " in packet {} at byte {}", packet_index, offset)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PacketError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.utf8_error.as_ref().map(|error| error as _)
+    }
+}
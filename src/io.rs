@@ -0,0 +1,326 @@
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use crate::{Header, Packet, PacketBuilder, PacketError, PacketErrorKind};
+
+impl From<PacketError> for io::Error {
+    fn from(error: PacketError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// Splits everything written to it into packets and writes them to the inner writer.
+///
+/// A packet is emitted as soon as enough bytes for a full packet have been written.
+/// Leftover bytes are sent as a shorter packet on `flush` or when the writer is dropped.
+/// Invalid builder settings are reported as errors by `write`, as are
+/// [sequenced](PacketBuilder::sequenced), [compressing](PacketBuilder::compression) and
+/// [parity](PacketBuilder::parity) builders. With a [framed](PacketBuilder::framed) builder,
+/// [`PacketWriter::end_message`] marks where each message ends.
+#[derive(Debug)]
+pub struct PacketWriter<W: Write> {
+    inner: Option<W>,
+    builder: PacketBuilder,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> PacketWriter<W> {
+    pub fn new(inner: W, builder: PacketBuilder) -> Self {
+        PacketWriter {
+            inner: Some(inner),
+            builder,
+            buffer: Vec::with_capacity(builder.packet_size as usize),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Flushes the pending bytes and returns the inner writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush_buffer(false)?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn emit(&mut self, payload: &[u8], ends_message: bool) -> io::Result<()> {
+        // Packets are sent before the message is complete, so they cannot be numbered,
+        // compressed or covered by a parity packet.
+        if self.builder.message_id.is_some()
+            || self.builder.compression.is_some()
+            || self.builder.parity.is_some()
+        {
+            return Err(PacketError::from(PacketErrorKind::UnsupportedByWriter).into());
+        }
+        self.builder.validate()?;
+        let (packet, _) = self.builder.build_at(payload, 0, 1, ends_message);
+        self.inner.as_mut().unwrap().write_all(&packet.serialize())
+    }
+
+    /// Sends the pending bytes as the last packet of the current message.
+    ///
+    /// An empty packet is sent if nothing is pending, so that the message is still terminated.
+    pub fn end_message(&mut self) -> io::Result<()> {
+        self.flush_buffer(true)
+    }
+
+    fn flush_buffer(&mut self, ends_message: bool) -> io::Result<()> {
+        if self.buffer.is_empty() && !ends_message {
+            return Ok(());
+        }
+
+        let buffer = std::mem::take(&mut self.buffer);
+        let result = self.emit(&buffer, ends_message);
+        self.buffer = buffer;
+        // The bytes were already accepted by `write`, so keep them for the next attempt.
+        if result.is_ok() {
+            self.buffer.clear();
+        }
+
+        result
+    }
+}
+
+impl<W: Write> Write for PacketWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let packet_size = self.builder.packet_size as usize;
+        let mut consumed = 0;
+        // Bytes that already went out must not be written again by a retry.
+        let partial = |consumed, error| match consumed {
+            0 => Err(error),
+            consumed => Ok(consumed),
+        };
+
+        if !self.buffer.is_empty() {
+            consumed = (packet_size - self.buffer.len()).min(buf.len());
+            self.buffer.extend_from_slice(&buf[..consumed]);
+
+            if self.buffer.len() < packet_size {
+                return Ok(consumed);
+            }
+            if let Err(error) = self.flush_buffer(false) {
+                return partial(consumed, error);
+            }
+        }
+
+        while buf.len() - consumed >= packet_size {
+            let payload = &buf[consumed..(consumed + packet_size)];
+            if let Err(error) = self.emit(payload, false) {
+                return partial(consumed, error);
+            }
+            consumed += packet_size;
+        }
+
+        self.buffer.extend_from_slice(&buf[consumed..]);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buffer(false)?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for PacketWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush();
+        }
+    }
+}
+
+/// Reads a packet stream from the inner reader and yields the concatenated payloads.
+///
+/// Every packet is validated before any of its payload is returned. Invalid packets are
+/// reported as `io::ErrorKind::InvalidData` errors wrapping the [`PacketError`], which can be
+/// recovered with `error.get_ref()` and `downcast_ref::<PacketError>()`. Its location is
+/// counted from the first byte read from the inner reader. Encrypted packets are reported as
+/// [`PacketErrorKind::EncryptedPacket`], compressed ones as
+/// [`PacketErrorKind::CompressedPacket`] as their messages can only be decoded as a whole,
+/// and sequenced ones as [`PacketErrorKind::SequencedPacket`] as they may arrive out of order.
+#[derive(Debug)]
+pub struct PacketReader<R: Read> {
+    inner: R,
+    packet: Vec<u8>,
+    payload: Range<usize>,
+    ends_message: bool,
+    offset: usize,
+    packets: usize,
+}
+
+impl<R: Read> PacketReader<R> {
+    pub fn new(inner: R) -> Self {
+        PacketReader {
+            inner,
+            packet: Vec::new(),
+            payload: 0..0,
+            ends_message: false,
+            offset: 0,
+            packets: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads the rest of the current message, up to and including a packet marked as last.
+    ///
+    /// Returns `None` at the end of the stream. A stream that ends in the middle of a message
+    /// is reported as [`PacketErrorKind::IncompleteMessage`].
+    pub fn read_message(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut message = Vec::new();
+        let mut started = !self.payload.is_empty();
+
+        loop {
+            message.extend_from_slice(&self.packet[self.payload.clone()]);
+            self.payload.start = self.payload.end;
+
+            if self.ends_message {
+                self.ends_message = false;
+                return Ok(Some(message));
+            }
+            if !self.next_packet()? {
+                if started {
+                    return Err(self.locate(PacketErrorKind::IncompleteMessage.into()));
+                }
+                return Ok(None);
+            }
+            started = true;
+        }
+    }
+
+    /// Reads the next packet, returning `false` on a clean end of stream.
+    ///
+    /// Parity packets are skipped, as the reader does not keep the packets they would repair.
+    fn next_packet(&mut self) -> io::Result<bool> {
+        loop {
+            self.offset += self.packet.len();
+            self.packet.clear();
+            self.payload = 0..0;
+            self.ends_message = false;
+
+            let header = loop {
+                match Header::parse(&self.packet) {
+                    Ok(Some(header)) => break header,
+                    Ok(None) => {}
+                    Err(error) => return Err(self.locate(error)),
+                }
+                // Every packet is at least this long, so this never reads into the next one.
+                let length = match Header::minimum_length(&self.packet) {
+                    Ok(length) => length,
+                    Err(error) => return Err(self.locate(error)),
+                };
+                if !self.fill(length)? {
+                    return Ok(false);
+                }
+            };
+
+            self.fill(header.packet_length())?;
+            let packet = match Packet::deserialize(&self.packet) {
+                Ok((packet, _)) => packet,
+                Err(error) => return Err(self.locate(error)),
+            };
+            if packet.is_parity() {
+                self.packets += 1;
+                continue;
+            }
+            if packet.is_encrypted() {
+                return Err(self.locate(PacketErrorKind::EncryptedPacket.into()));
+            }
+            // Only whole messages can be decompressed, which the reader does not keep.
+            if packet.is_compressed() {
+                return Err(self.locate(PacketErrorKind::CompressedPacket.into()));
+            }
+            // Payloads are returned as they arrive, so they could not be put back in order.
+            if packet.sequence().is_some() {
+                return Err(self.locate(PacketErrorKind::SequencedPacket.into()));
+            }
+
+            let start = header.header_length();
+            self.payload = start..(start + packet.payload().len());
+            self.ends_message = packet.is_last();
+            self.packets += 1;
+
+            return Ok(true);
+        }
+    }
+
+    /// Reads until the packet buffer holds `length` bytes.
+    ///
+    /// Returns `false` if the stream ended before the first byte of a packet.
+    fn fill(&mut self, length: usize) -> io::Result<bool> {
+        while self.packet.len() < length {
+            let start = self.packet.len();
+            self.packet.resize(length, 0);
+
+            let read = match self.inner.read(&mut self.packet[start..]) {
+                Ok(read) => read,
+                Err(error) => {
+                    self.packet.truncate(start);
+                    if error.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(error);
+                }
+            };
+            self.packet.truncate(start + read);
+
+            if read == 0 {
+                if start == 0 {
+                    return Ok(false);
+                }
+                // The header may already be complete and tell the actual length of the packet.
+                let declared = match Header::parse(&self.packet) {
+                    Ok(Some(header)) => header.packet_length(),
+                    _ => length,
+                };
+                let error = PacketErrorKind::InvalidPacket {
+                    declared,
+                    available: start,
+                };
+                return Err(self.locate(error.into()));
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn locate(&self, error: PacketError) -> io::Error {
+        error.at(self.offset, self.packets).into()
+    }
+}
+
+impl<R: Read> Read for PacketReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while self.payload.is_empty() {
+            if !self.next_packet()? {
+                return Ok(0);
+            }
+        }
+
+        let available = &self.packet[self.payload.clone()];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.payload.start += count;
+
+        Ok(count)
+    }
+}
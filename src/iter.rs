@@ -0,0 +1,226 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::reassembly::{Slots, MIN_SEQUENCED_PACKET_LENGTH};
+#[cfg(feature = "alloc")]
+use crate::PacketErrorKind;
+use crate::{Packet, PacketError};
+
+/// Iterates over the packets of a byte buffer, borrowing their payloads from it.
+///
+/// Errors carry their location in the buffer, and the iterator stops after the first one.
+#[derive(Debug)]
+pub struct PacketIter<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    index: usize,
+    failed: bool,
+}
+
+impl<'a> PacketIter<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        PacketIter {
+            bytes,
+            position: 0,
+            index: 0,
+            failed: false,
+        }
+    }
+
+    /// The bytes that have not been decoded yet.
+    pub fn remainder(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+
+    #[cfg(feature = "alloc")]
+    /// Validates the remaining packets and returns a view of their payloads in message order.
+    ///
+    /// Sequenced packets are put back in order, and a message that is still missing packets
+    /// at the end of the buffer, or that has more packets than the rest of the buffer could
+    /// hold, is reported as [`PacketErrorKind::IncompleteMessage`].
+    /// Compressed and uncompressed packets cannot be mixed, see
+    /// [`PayloadChunks::is_compressed`], and encrypted packets are reported as
+    /// [`PacketErrorKind::EncryptedPacket`]. Parity packets are skipped, but
+    /// those covering more packets than were found are reported as
+    /// [`PacketErrorKind::IncompleteMessage`].
+    pub fn payload_chunks(mut self) -> Result<PayloadChunks<'a>, PacketError> {
+        let mut chunks = Vec::new();
+        let mut slots = Slots::default();
+        let mut compressed = None;
+        let mut group = 0;
+
+        loop {
+            let (offset, index) = (self.position, self.index);
+            let packet = match self.next() {
+                Some(result) => result?,
+                None => break,
+            };
+            if packet.is_encrypted() {
+                let error = PacketError::from(PacketErrorKind::EncryptedPacket);
+                return Err(error.at(offset, index));
+            }
+            if packet.is_parity() {
+                // Packets lost on the way leave a group smaller than its parity packet says.
+                if matches!(packet.payload().first(), Some(&count) if group < count as usize) {
+                    let error = PacketError::from(PacketErrorKind::IncompleteMessage);
+                    return Err(error.at(offset, index));
+                }
+                group = 0;
+                continue;
+            }
+            group += 1;
+            if *compressed.get_or_insert(packet.is_compressed()) != packet.is_compressed() {
+                let error = PacketError::from(PacketErrorKind::InvalidFlags(packet.flags));
+                return Err(error.at(offset, index));
+            }
+
+            match packet.sequence() {
+                Some(sequence) => {
+                    // The packet count comes from the wire, so check that the input could
+                    // still hold the missing packets before taking it at its word.
+                    let outstanding = slots.outstanding(sequence);
+                    if outstanding * MIN_SEQUENCED_PACKET_LENGTH > self.remainder().len() {
+                        let error = PacketError::from(PacketErrorKind::IncompleteMessage);
+                        return Err(error.at(offset, index));
+                    }
+                    let message = slots
+                        .push(sequence, packet.payload())
+                        .map_err(|error| error.at(offset, index))?;
+                    chunks.extend(message.into_iter().flatten());
+                }
+                None => chunks.push(packet.payload()),
+            }
+        }
+
+        if slots.pending() > 0 {
+            let error = PacketError::from(PacketErrorKind::IncompleteMessage);
+            return Err(error.at(self.position, self.index));
+        }
+
+        chunks.retain(|chunk| !chunk.is_empty());
+        let remaining = chunks.iter().map(|chunk| chunk.len()).sum();
+
+        Ok(PayloadChunks {
+            chunks,
+            current: 0,
+            offset: 0,
+            remaining,
+            compressed: compressed.unwrap_or(false),
+        })
+    }
+}
+
+impl<'a> Iterator for PacketIter<'a> {
+    type Item = Result<Packet<'a>, PacketError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.position >= self.bytes.len() {
+            return None;
+        }
+
+        match Packet::deserialize(self.remainder()) {
+            Ok((packet, remainder)) => {
+                self.position = self.bytes.len() - remainder.len();
+                self.index += 1;
+                Some(Ok(packet))
+            }
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error.at(self.position, self.index)))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// The payloads of a message as a sequence of borrowed chunks, read like a `bytes::Buf`.
+///
+/// Empty payloads are left out, so [`PayloadChunks::chunk`] only returns an empty slice once
+/// everything has been read. Iterating yields the unread part of every chunk.
+#[derive(Clone, Debug)]
+pub struct PayloadChunks<'a> {
+    chunks: Vec<&'a [u8]>,
+    current: usize,
+    offset: usize,
+    remaining: usize,
+    compressed: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> PayloadChunks<'a> {
+    /// Number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn has_remaining(&self) -> bool {
+        self.remaining > 0
+    }
+
+    /// Whether the chunks hold a compressed message, which [`Packetable::from_packet_data`]
+    /// decompresses.
+    ///
+    /// [`Packetable::from_packet_data`]: crate::Packetable::from_packet_data
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// The unread part of the current chunk.
+    pub fn chunk(&self) -> &'a [u8] {
+        match self.chunks.get(self.current) {
+            Some(chunk) => &chunk[self.offset..],
+            None => &[],
+        }
+    }
+
+    /// Skips `count` bytes, moving on to the following chunks as needed.
+    ///
+    /// Panics if fewer than `count` bytes remain.
+    pub fn advance(&mut self, mut count: usize) {
+        assert!(count <= self.remaining, "cannot advance past the end");
+        self.remaining -= count;
+
+        while count > 0 {
+            let available = self.chunks[self.current].len() - self.offset;
+            if count < available {
+                self.offset += count;
+                return;
+            }
+            count -= available;
+            self.current += 1;
+            self.offset = 0;
+        }
+    }
+
+    /// Fills `destination` from the unread bytes.
+    ///
+    /// Panics if fewer bytes than `destination.len()` remain.
+    pub fn copy_to_slice(&mut self, destination: &mut [u8]) {
+        assert!(destination.len() <= self.remaining, "not enough bytes left");
+
+        let mut filled = 0;
+        while filled < destination.len() {
+            let chunk = self.chunk();
+            let count = chunk.len().min(destination.len() - filled);
+            destination[filled..(filled + count)].copy_from_slice(&chunk[..count]);
+            self.advance(count);
+            filled += count;
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> Iterator for PayloadChunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.chunk();
+        if chunk.is_empty() {
+            return None;
+        }
+        self.advance(chunk.len());
+
+        Some(chunk)
+    }
+}
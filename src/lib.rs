@@ -1,46 +1,365 @@
-use std::convert::TryInto;
-use std::fmt;
+//! Splits messages into checksummed packets and puts them back together.
+//!
+//! The packet format itself builds without the standard library. The `alloc` feature adds
+//! everything that needs owned buffers, such as [`Packet::serialize`] and [`Packetable`],
+//! and the `std` feature (on by default) adds the `Error` impl and the `io` adapters. The
+//! `async` feature adds `tokio_util` codecs for packets and whole messages, and the `mac`
+//! feature keyed HMAC-SHA256 tags in place of the checksum.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+mod checksum;
+#[cfg(feature = "async")]
+mod codec;
+mod compression;
+mod decoder;
+mod dump;
+#[cfg(feature = "alloc")]
+pub mod encoding;
+#[cfg(feature = "aead")]
+mod encryption;
+mod error;
+#[cfg(feature = "std")]
+mod io;
+mod iter;
+#[cfg(feature = "mac")]
+mod mac;
+#[cfg(feature = "alloc")]
+mod packetable;
+mod parity;
+mod reassembly;
+mod recovery;
+
+pub use checksum::{Additive, Adler32, Checksum, ChecksumAlgorithm, Crc32, Crc32c, MacLength};
+#[cfg(feature = "async")]
+pub use codec::{MessageCodec, PacketCodec, DEFAULT_MAX_MESSAGE_LENGTH};
+pub use compression::Compression;
+#[cfg(feature = "alloc")]
+pub use compression::DEFAULT_DECOMPRESSION_LIMIT;
+pub use decoder::Decoded;
+#[cfg(feature = "alloc")]
+pub use decoder::{PacketBuf, PacketDecoder, Packets};
+pub use dump::{HexDump, StreamDump};
+#[cfg(feature = "alloc")]
+pub use encoding::PacketField;
+#[cfg(feature = "aead")]
+pub use encryption::{CipherAlgorithm, PacketCipher, SEAL_OVERHEAD};
+pub use error::{PacketError, PacketErrorKind};
+#[cfg(feature = "std")]
+pub use io::{PacketReader, PacketWriter};
+pub use iter::PacketIter;
+#[cfg(feature = "alloc")]
+pub use iter::PayloadChunks;
+#[cfg(feature = "mac")]
+pub use mac::MacKey;
+#[cfg(feature = "alloc")]
+pub use packetable::{from_packet_data_lossy, Packetable};
+#[cfg(feature = "alloc")]
+pub use reassembly::Reassembler;
+pub use reassembly::Sequence;
+pub use reassembly::MAX_PENDING_MESSAGES;
+pub use recovery::{Recovered, Resync, Skipped};
+
+#[cfg(feature = "alloc")]
+use parity::ParityGroup;
+use parity::PARITY_OVERHEAD;
+use reassembly::SEQUENCE_LENGTH;
+#[cfg(feature = "derive")]
+pub use solution_derive::Packetable;
+
+/// Paths used by the code generated by `#[derive(Packetable)]`.
+#[cfg(feature = "alloc")]
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+}
 
-#[derive(Debug, PartialEq)]
-pub enum PacketError {
-    InvalidPacket,
-    InvalidChecksum,
-    UnknownProtocolVersion,
-    CorruptedMessage,
+const CHECKSUM_LENGTH: usize = 4;
+const MAX_CHECKSUM_LENGTH: usize = 32;
+const CHECKSUM_ALGORITHM_MASK: u8 = 0b0000_0111;
+const SEQUENCED_FLAG: u8 = 0b0000_1000;
+const LAST_FLAG: u8 = 0b0001_0000;
+const COMPRESSED_FLAG: u8 = 0b0010_0000;
+const ENCRYPTED_FLAG: u8 = 0b0100_0000;
+const PARITY_FLAG: u8 = 0b1000_0000;
+const MAX_HEADER_LENGTH: usize = 4 + SEQUENCE_LENGTH;
+
+/// Wire format of a packet.
+///
+/// Version 1 is `[1, size: u8, payload, checksum: [u8; 4]]` and always uses the additive checksum
+/// of the payload. Version 2 is `[2, flags: u8, size: u16 (big endian), payload, checksum: [u8; 4]
+/// or tag]`, where the checksum covers the header as well as the payload. The low three bits of
+/// the flags byte hold the id of the [`ChecksumAlgorithm`] or, as ids 4 to 7, of the
+/// [`MacLength`] of an HMAC-SHA256 tag that replaces the checksum. The other bits are flags:
+///
+/// * `0b0000_1000` - the size is followed by a [`Sequence`] as three big endian `u16`s:
+///   message id, packet index and packet count.
+/// * `0b0001_0000` - the packet is the last one of its message.
+/// * `0b0010_0000` - the payloads of the message, joined together, are compressed and start
+///   with the id of the [`Compression`] codec.
+/// * `0b0100_0000` - the payload is sealed with an AEAD cipher: a big endian `u64` counter
+///   followed by the ciphertext and its 16 byte tag, see `PacketCipher`.
+/// * `0b1000_0000` - the packet holds the parity of the data packets in front of it, see
+///   [`PacketBuilder::parity`]. The payload is the number of packets covered, followed by
+///   the XOR of their flags, size, sequence (zeros when missing) and zero padded payloads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    V1 = 1,
+    V2 = 2,
 }
 
-impl fmt::Display for PacketError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl ProtocolVersion {
+    pub fn from_byte(byte: u8) -> Result<Self, PacketError> {
+        match byte {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            _ => Err(PacketErrorKind::UnknownProtocolVersion(byte).into()),
+        }
+    }
+
+    pub fn max_packet_size(self) -> usize {
         match self {
-            Self::InvalidPacket => write!(f, "Invalid packet"),
-            Self::InvalidChecksum => write!(f, "Checksum invalid"),
-            Self::UnknownProtocolVersion => write!(f, "Unknown protocol version"),
-            Self::CorruptedMessage => write!(f, "Data is corrupted"),
+            Self::V1 => u8::MAX as usize,
+            Self::V2 => u16::MAX as usize,
         }
     }
-}
 
-impl std::error::Error for PacketError {}
+    fn header_length(self) -> usize {
+        match self {
+            Self::V1 => 2,
+            Self::V2 => 4,
+        }
+    }
+}
 
-#[derive(PartialEq, Debug)]
-pub struct Packet<'a> {
-    version: u8,
-    size: u8,
-    payload: &'a [u8],
-    checksum: [u8; 4],
+/// Settings used when splitting a source into packets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PacketBuilder {
+    version: ProtocolVersion,
+    packet_size: u16,
+    checksum: ChecksumAlgorithm,
+    message_id: Option<u16>,
+    framed: bool,
+    compression: Option<Compression>,
+    parity: Option<u8>,
+    #[cfg(feature = "mac")]
+    mac: Option<MacKey>,
 }
 
-impl<'a> Packet<'a> {
-    pub fn from_source(source: &'a [u8], size: u8) -> (Self, &[u8]) {
-        if size == 0 {
-            panic!();
+impl PacketBuilder {
+    pub fn new(packet_size: u16) -> Self {
+        PacketBuilder {
+            version: ProtocolVersion::V2,
+            packet_size,
+            checksum: ChecksumAlgorithm::Additive,
+            message_id: None,
+            framed: false,
+            compression: None,
+            parity: None,
+            #[cfg(feature = "mac")]
+            mac: None,
+        }
+    }
+
+    fn v1(packet_size: u8) -> Self {
+        Self::new(packet_size.into()).version(ProtocolVersion::V1)
+    }
+
+    pub fn version(mut self, version: ProtocolVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn packet_size(mut self, packet_size: u16) -> Self {
+        self.packet_size = packet_size;
+        self
+    }
+
+    pub fn checksum(mut self, checksum: ChecksumAlgorithm) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Numbers the packets of every source with the given message id, see [`Sequence`].
+    pub fn sequenced(mut self, message_id: u16) -> Self {
+        self.message_id = Some(message_id);
+        self
+    }
+
+    /// Marks the last packet of every source, so that several messages can share a stream.
+    ///
+    /// Empty sources are sent as a single empty packet.
+    pub fn framed(mut self) -> Self {
+        self.framed = true;
+        self
+    }
+
+    /// Compresses every source as a whole before splitting it into packets.
+    ///
+    /// Only the methods that produce packet data, such as [`ToPackets::to_packet_data_with`]
+    /// and [`Packetable`], compress. Packets built one at a time with [`PacketBuilder::build`]
+    /// or [`PacketBuilder::packets`] borrow the source and carry it as it is.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Follows every `group_size` packets of a source, and the packets left over at its end,
+    /// with a parity packet from which [`Packetable::from_packet_data_with_repair`] can
+    /// rebuild one damaged or missing packet of the group.
+    ///
+    /// As with [compression](PacketBuilder::compression), only the methods that produce packet
+    /// data add parity packets. Parity packets are skipped when messages are decoded, and
+    /// [`Packetable::next_message`] takes the ones that follow the end of a message with it.
+    pub fn parity(mut self, group_size: u8) -> Self {
+        self.parity = Some(group_size);
+        self
+    }
+
+    /// Replaces the checksum of every packet with an HMAC-SHA256 tag, which only holders of the
+    /// key can forge. Such packets have to be read with [`Packet::deserialize_with_key`] or
+    /// [`MacKey::open`].
+    #[cfg(feature = "mac")]
+    pub fn mac(mut self, key: MacKey) -> Self {
+        self.mac = Some(key);
+        self
+    }
+
+    /// Checks that the settings can be put on the wire.
+    ///
+    /// The packet size must be between 1 and the maximum of the protocol version, and version 1
+    /// supports neither checksums other than the additive one, nor MACs, sequencing, framing,
+    /// compression or parity. Compression codecs must be enabled, see
+    /// [`Compression::is_enabled`]. Parity groups cannot be empty, and parity packets, which
+    /// are a few bytes larger than the packet size, must not exceed the maximum either. They
+    /// also need the `alloc` feature, and are rejected as [`PacketErrorKind::InvalidFlags`]
+    /// without it.
+    pub fn validate(&self) -> Result<(), PacketError> {
+        let size = self.packet_size as usize;
+        if size == 0 || size > self.version.max_packet_size() {
+            return Err(PacketErrorKind::InvalidPacketSize.into());
+        }
+        if self.version == ProtocolVersion::V1
+            && (self.checksum != ChecksumAlgorithm::Additive
+                || self.message_id.is_some()
+                || self.framed
+                || self.compression.is_some()
+                || self.parity.is_some())
+        {
+            return Err(PacketErrorKind::UnsupportedByVersion.into());
+        }
+        if let Some(group_size) = self.parity {
+            if group_size == 0 || size + PARITY_OVERHEAD > self.version.max_packet_size() {
+                return Err(PacketErrorKind::InvalidPacketSize.into());
+            }
+            // Parity packets are built in memory, which would otherwise silently leave them out.
+            if cfg!(not(feature = "alloc")) {
+                return Err(PacketErrorKind::InvalidFlags(PARITY_FLAG).into());
+            }
+        }
+        #[cfg(feature = "mac")]
+        if self.version == ProtocolVersion::V1 && self.mac.is_some() {
+            return Err(PacketErrorKind::UnsupportedByVersion.into());
+        }
+        if let Some(compression) = self.compression {
+            if !compression.is_enabled() {
+                return Err(PacketErrorKind::UnknownCompression(compression.id()).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The id stored in the low bits of the flags byte.
+    fn checksum_id(&self) -> u8 {
+        #[cfg(feature = "mac")]
+        if let Some(key) = self.mac {
+            return key.length().id();
+        }
+        self.checksum.id()
+    }
+
+    fn checksum_of(&self, packet: &Packet) -> [u8; MAX_CHECKSUM_LENGTH] {
+        #[cfg(feature = "mac")]
+        if let Some(key) = self.mac {
+            return key.tag(packet);
+        }
+        checksum_field(packet.compute_checksum(self.checksum))
+    }
+
+    /// A parity packet carrying `payload`, see [`PacketBuilder::parity`].
+    #[cfg(feature = "alloc")]
+    pub(crate) fn parity_packet<'a>(&self, payload: &'a [u8]) -> Packet<'a> {
+        let mut packet = Packet {
+            version: self.version as u8,
+            flags: self.checksum_id() | PARITY_FLAG,
+            size: payload.len().try_into().unwrap(),
+            sequence: None,
+            payload,
+            checksum: [0; MAX_CHECKSUM_LENGTH],
+        };
+        packet.checksum = self.checksum_of(&packet);
+
+        packet
+    }
+
+    /// Panics if the settings are invalid, see [`PacketBuilder::try_build`].
+    pub fn build<'a>(&self, source: &'a [u8]) -> (Packet<'a>, &'a [u8]) {
+        self.try_build(source).unwrap()
+    }
+
+    pub fn try_build<'a>(&self, source: &'a [u8]) -> Result<(Packet<'a>, &'a [u8]), PacketError> {
+        let count = self.checked_packet_count(source.len())?;
+        let ends_message = source.len() <= self.packet_size as usize;
+
+        Ok(self.build_at(source, 0, count, ends_message))
+    }
+
+    fn checked_packet_count(&self, source_length: usize) -> Result<usize, PacketError> {
+        self.validate()?;
+
+        let count = source_length.div_ceil(self.packet_size as usize).max(1);
+        if self.message_id.is_some() && count > u16::MAX as usize {
+            return Err(PacketErrorKind::MessageTooLong.into());
+        }
+
+        Ok(count)
+    }
+
+    /// Builds a packet from settings that have already been validated.
+    pub(crate) fn build_at<'a>(
+        &self,
+        source: &'a [u8],
+        index: usize,
+        count: usize,
+        ends_message: bool,
+    ) -> (Packet<'a>, &'a [u8]) {
+        let size = self.packet_size as usize;
+        let sequence = self.message_id.map(|message_id| Sequence {
+            message_id,
+            index: index.try_into().unwrap(),
+            count: count.try_into().unwrap(),
+        });
+        let mut flags = self.checksum_id();
+        if sequence.is_some() {
+            flags |= SEQUENCED_FLAG;
+        }
+        if self.framed && ends_message {
+            flags |= LAST_FLAG;
         }
 
         let payload: &[u8];
         let remainder: &[u8];
 
         let source_length = source.len();
-        let mut parsed_size = size as usize;
+        let mut parsed_size = size;
 
         if source_length > parsed_size {
             payload = &source[0..parsed_size];
@@ -51,133 +370,493 @@ impl<'a> Packet<'a> {
             parsed_size = source_length;
         }
 
-        let checksum: [u8; 4] = Self::find_checksum(payload);
+        let mut packet = Packet {
+            version: self.version as u8,
+            flags,
+            size: parsed_size.try_into().unwrap(),
+            sequence,
+            payload,
+            checksum: [0; MAX_CHECKSUM_LENGTH],
+        };
+        packet.checksum = self.checksum_of(&packet);
 
-        (
-            Packet {
-                version: 1,
-                size: parsed_size.try_into().unwrap(),
-                payload,
-                checksum,
-            },
-            remainder,
-        )
+        (packet, remainder)
+    }
+
+    /// Panics if the settings are invalid, see [`PacketBuilder::try_packets`].
+    pub fn packets<'a>(&self, source: &'a [u8]) -> PacketSerializer<'a> {
+        self.try_packets(source).unwrap()
+    }
+
+    pub fn try_packets<'a>(&self, source: &'a [u8]) -> Result<PacketSerializer<'a>, PacketError> {
+        Ok(PacketSerializer {
+            builder: *self,
+            remaining_bytes: source,
+            index: 0,
+            count: self.checked_packet_count(source.len())?,
+        })
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Packet<'a> {
+    version: u8,
+    flags: u8,
+    size: u16,
+    sequence: Option<Sequence>,
+    payload: &'a [u8],
+    checksum: [u8; MAX_CHECKSUM_LENGTH],
+}
+
+impl<'a> Packet<'a> {
+    pub fn from_source(source: &'a [u8], size: u8) -> (Self, &'a [u8]) {
+        PacketBuilder::v1(size).build(source)
+    }
+
+    pub fn try_from_source(source: &'a [u8], size: u8) -> Result<(Self, &'a [u8]), PacketError> {
+        PacketBuilder::v1(size).try_build(source)
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// `None` for packets carrying a MAC instead, see [`Packet::mac_length`].
+    pub fn checksum_algorithm(&self) -> Option<ChecksumAlgorithm> {
+        ChecksumAlgorithm::from_id(self.flags & CHECKSUM_ALGORITHM_MASK).ok()
+    }
+
+    /// The length of the HMAC-SHA256 tag replacing the checksum, see `PacketBuilder::mac`.
+    pub fn mac_length(&self) -> Option<MacLength> {
+        MacLength::from_id(self.flags & CHECKSUM_ALGORITHM_MASK)
     }
 
-    pub fn payload(&self) -> &[u8] {
+    pub fn sequence(&self) -> Option<Sequence> {
+        self.sequence
+    }
+
+    /// Whether the packet was marked as the end of its message, see [`PacketBuilder::framed`].
+    pub fn is_last(&self) -> bool {
+        self.flags & LAST_FLAG != 0
+    }
+
+    /// Whether the packet belongs to a compressed message, see [`PacketBuilder::compression`].
+    pub fn is_compressed(&self) -> bool {
+        self.flags & COMPRESSED_FLAG != 0
+    }
+
+    /// Whether the payload is encrypted, in which case it has to be opened with the
+    /// `PacketCipher` that sealed it before the message can be decoded.
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & ENCRYPTED_FLAG != 0
+    }
+
+    /// Whether the packet holds the parity of the packets in front of it rather than part of
+    /// a message, see [`PacketBuilder::parity`].
+    pub fn is_parity(&self) -> bool {
+        self.flags & PARITY_FLAG != 0
+    }
+
+    pub fn payload(&self) -> &'a [u8] {
         self.payload
     }
 
-    pub fn serialize(&self) -> Vec<u8> {
-        let mut bytes: Vec<u8> = vec![self.version, self.size];
+    /// The checksum or MAC carried by the packet, which may not match its payload.
+    pub fn checksum(&self) -> &[u8] {
+        &self.checksum[..checksum_length(self.flags)]
+    }
 
-        bytes.extend_from_slice(self.payload);
-        bytes.extend(self.checksum.iter().cloned());
+    #[cfg(feature = "alloc")]
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        self.write_to(&mut bytes);
 
         bytes
     }
 
-    pub fn deserialize(bytes: &[u8]) -> Result<(Packet, &[u8]), PacketError> {
-        let reserved_bytes_count = 6_usize;
+    /// Number of bytes taken up by the serialized packet.
+    pub fn encoded_len(&self) -> usize {
+        self.header().1 + self.payload.len() + self.checksum().len()
+    }
+
+    /// Serializes the packet to the start of `buf` and returns the number of bytes written.
+    ///
+    /// Fails with [`PacketErrorKind::BufferTooSmall`] if `buf` is shorter than
+    /// [`Packet::encoded_len`], in which case nothing is written.
+    pub fn serialize_into(&self, buf: &mut [u8]) -> Result<usize, PacketError> {
+        let length = self.encoded_len();
+        if buf.len() < length {
+            return Err(PacketErrorKind::BufferTooSmall {
+                required: length,
+                available: buf.len(),
+            }
+            .into());
+        }
+
+        let (header, header_length) = self.header();
+        let (header_bytes, rest) = buf.split_at_mut(header_length);
+        let (payload_bytes, rest) = rest.split_at_mut(self.payload.len());
+        header_bytes.copy_from_slice(&header[..header_length]);
+        payload_bytes.copy_from_slice(self.payload);
+        rest[..self.checksum().len()].copy_from_slice(self.checksum());
+
+        Ok(length)
+    }
+
+    /// Appends the serialized packet to `out`.
+    pub(crate) fn write_to(&self, out: &mut impl Extend<u8>) {
+        let (header, header_length) = self.header();
+        out.extend(header[..header_length].iter().copied());
+        out.extend(self.payload.iter().copied());
+        out.extend(self.checksum().iter().copied());
+    }
+
+    /// The serialized fields in front of the payload, and how many bytes of the array they use.
+    fn header(&self) -> ([u8; MAX_HEADER_LENGTH], usize) {
+        let mut header = [0; MAX_HEADER_LENGTH];
+        header[0] = self.version;
+
+        if self.version == ProtocolVersion::V1 as u8 {
+            header[1] = self.size as u8;
+            return (header, ProtocolVersion::V1.header_length());
+        }
+
+        header[1] = self.flags;
+        header[2..4].copy_from_slice(&self.size.to_be_bytes());
+        match self.sequence {
+            Some(sequence) => {
+                header[4..].copy_from_slice(&sequence.to_bytes());
+                (header, MAX_HEADER_LENGTH)
+            }
+            None => (header, ProtocolVersion::V2.header_length()),
+        }
+    }
+
+    pub fn deserialize(bytes: &'a [u8]) -> Result<(Packet<'a>, &'a [u8]), PacketError> {
+        let (packet, remainder) = Self::deserialize_unverified(bytes)?;
+        packet.verify_checksum()?;
+
+        Ok((packet, remainder))
+    }
+
+    /// Like [`Packet::deserialize`], but accepts packets whose checksum does not match, so
+    /// that damaged packets can still be inspected. See [`Packet::verify_checksum`].
+    pub fn deserialize_unverified(bytes: &'a [u8]) -> Result<(Packet<'a>, &'a [u8]), PacketError> {
+        let minimum_length = ProtocolVersion::V1.header_length() + CHECKSUM_LENGTH;
 
         let byte_count = bytes.len();
-        if byte_count < reserved_bytes_count {
-            return Err(PacketError::InvalidPacket);
+        let truncated = |declared| PacketErrorKind::InvalidPacket {
+            declared,
+            available: byte_count,
+        };
+        if byte_count < minimum_length {
+            return Err(truncated(minimum_length).into());
         }
 
-        let version = match bytes[0] {
-            1 => 1,
-            _ => return Err(PacketError::UnknownProtocolVersion),
+        let header = match Header::parse(bytes)? {
+            Some(header) => header,
+            None => return Err(truncated(Header::minimum_length(bytes)?).into()),
         };
+        if header.packet_length() > byte_count {
+            return Err(truncated(header.packet_length()).into());
+        }
 
-        let size = bytes[1] as usize;
-        if size > (byte_count - reserved_bytes_count) {
-            return Err(PacketError::InvalidPacket);
+        Ok(Self::split(&header, bytes))
+    }
+
+    /// Fails with [`PacketErrorKind::MissingKey`] for packets carrying a MAC, which can only be
+    /// verified with `Packet::verify_mac`.
+    pub fn verify_checksum(&self) -> Result<(), PacketError> {
+        let algorithm = self
+            .checksum_algorithm()
+            .ok_or(PacketErrorKind::MissingKey)?;
+        let expected = self.checksum[..CHECKSUM_LENGTH].try_into().unwrap();
+        let computed = self.compute_checksum(algorithm);
+        if computed != expected {
+            return Err(PacketErrorKind::InvalidChecksum { expected, computed }.into());
         }
 
-        let payload = &bytes[2..(size + 2)];
-        let checksum_to_check = &bytes[(size + 2)..(size + reserved_bytes_count)];
-        let checksum = Self::find_checksum(payload);
-        if checksum != checksum_to_check {
-            return Err(PacketError::InvalidChecksum);
+        Ok(())
+    }
+
+    /// The checksum of the payload with `algorithm`, after the header for version 2 so that
+    /// changed flags, sizes and sequences are caught as well.
+    pub(crate) fn compute_checksum(&self, algorithm: ChecksumAlgorithm) -> [u8; 4] {
+        if self.version == ProtocolVersion::V1 as u8 {
+            return algorithm.checksum(self.payload);
         }
 
-        let remainder = &bytes[(size + reserved_bytes_count)..];
+        let (header, header_length) = self.header();
+        algorithm.checksum_parts(&[&header[..header_length], self.payload])
+    }
+
+    /// Splits off the packet described by `header` without verifying its checksum.
+    ///
+    /// `bytes` must hold at least `header.packet_length()` bytes.
+    pub(crate) fn split(header: &Header, bytes: &'a [u8]) -> (Packet<'a>, &'a [u8]) {
+        let header_length = header.header_length();
+        let size = header.size;
 
-        Ok((
+        let payload = &bytes[header_length..(size + header_length)];
+        let mut checksum = [0; MAX_CHECKSUM_LENGTH];
+        checksum[..header.checksum_length()]
+            .copy_from_slice(&bytes[(size + header_length)..header.packet_length()]);
+        let remainder = &bytes[header.packet_length()..];
+
+        (
             Packet {
-                version,
+                version: header.version as u8,
+                flags: header.flags,
                 size: size.try_into().unwrap(),
+                sequence: header.sequence,
                 payload,
                 checksum,
             },
             remainder,
-        ))
+        )
+    }
+}
+
+/// The fields in front of the payload, parsed without looking at the rest of the packet.
+#[derive(Debug)]
+pub(crate) struct Header {
+    version: ProtocolVersion,
+    flags: u8,
+    size: usize,
+    sequence: Option<Sequence>,
+}
+
+impl Header {
+    /// Returns `Ok(None)` when `bytes` is too short to contain the whole header.
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Option<Self>, PacketError> {
+        let version = match bytes.first() {
+            Some(&byte) => ProtocolVersion::from_byte(byte)?,
+            None => return Ok(None),
+        };
+        if bytes.len() < version.header_length() {
+            return Ok(None);
+        }
+
+        let (flags, size) = match version {
+            ProtocolVersion::V1 => (0, bytes[1] as usize),
+            ProtocolVersion::V2 => (bytes[1], u16::from_be_bytes([bytes[2], bytes[3]]) as usize),
+        };
+        let mut sequence = None;
+        if flags & SEQUENCED_FLAG != 0 {
+            let start = version.header_length();
+            match bytes.get(start..(start + SEQUENCE_LENGTH)) {
+                Some(sequence_bytes) => sequence = Some(Sequence::from_bytes(sequence_bytes)),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(Some(Header {
+            version,
+            flags,
+            size,
+            sequence,
+        }))
+    }
+
+    /// The least number of bytes a packet starting with `bytes` can take up, for use when
+    /// [`Header::parse`] found its header incomplete.
+    pub(crate) fn minimum_length(bytes: &[u8]) -> Result<usize, PacketError> {
+        let version = match bytes.first() {
+            Some(&byte) => ProtocolVersion::from_byte(byte)?,
+            None => ProtocolVersion::V1,
+        };
+        let flags = match (version, bytes.get(1)) {
+            (ProtocolVersion::V2, Some(&flags)) => flags,
+            _ => 0,
+        };
+        let mut length = version.header_length() + checksum_length(flags);
+        if flags & SEQUENCED_FLAG != 0 {
+            length += SEQUENCE_LENGTH;
+        }
+
+        Ok(length)
+    }
+
+    pub(crate) fn header_length(&self) -> usize {
+        match self.sequence {
+            Some(_) => self.version.header_length() + SEQUENCE_LENGTH,
+            None => self.version.header_length(),
+        }
+    }
+
+    pub(crate) fn checksum_length(&self) -> usize {
+        checksum_length(self.flags)
     }
 
-    fn find_checksum(payload: &[u8]) -> [u8; 4] {
-        let sum: u32 = payload.iter().map(|&byte| byte as u32).sum();
-        sum.to_be_bytes()
+    /// Length of the whole serialized packet, header and checksum included.
+    pub(crate) fn packet_length(&self) -> usize {
+        self.header_length() + self.size + self.checksum_length()
     }
 }
 
+/// Length of the checksum field of a packet with the given flags, which are 0 for version 1.
+fn checksum_length(flags: u8) -> usize {
+    match MacLength::from_id(flags & CHECKSUM_ALGORITHM_MASK) {
+        Some(length) => length.tag_length(),
+        None => CHECKSUM_LENGTH,
+    }
+}
+
+/// A checksum padded to the size of the packet's checksum field.
+pub(crate) fn checksum_field(checksum: [u8; CHECKSUM_LENGTH]) -> [u8; MAX_CHECKSUM_LENGTH] {
+    let mut field = [0; MAX_CHECKSUM_LENGTH];
+    field[..CHECKSUM_LENGTH].copy_from_slice(&checksum);
+    field
+}
+
 #[derive(Debug)]
 pub struct PacketSerializer<'a> {
-    packet_size: u8,
+    builder: PacketBuilder,
     remaining_bytes: &'a [u8],
+    index: usize,
+    count: usize,
 }
 
 impl<'a> Iterator for PacketSerializer<'a> {
     type Item = Packet<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.remaining_bytes.len() == 0 {
+        let sends_empty_message = self.builder.framed || self.builder.message_id.is_some();
+        if self.remaining_bytes.is_empty() && (self.index > 0 || !sends_empty_message) {
             return None;
         }
-        let (packet, remainder) = Packet::from_source(self.remaining_bytes, self.packet_size);
+        let ends_message = self.index + 1 == self.count;
+        let (packet, remainder) =
+            self.builder
+                .build_at(self.remaining_bytes, self.index, self.count, ends_message);
         self.remaining_bytes = remainder;
+        self.index += 1;
 
         Some(packet)
     }
 }
 
-pub trait Packetable: Sized {
-    fn to_packets(&self, packet_size: u8) -> PacketSerializer;
-    fn to_packet_data(&self, packet_size: u8) -> Vec<u8>;
-    fn from_packet_data(packet_data: &[u8]) -> Result<Self, PacketError>;
-}
+/// Encoding side of the packet format, implemented for borrowed byte sources.
+///
+/// Owned types such as `String` and `Vec<u8>` reach these methods through deref. The `try_`
+/// methods report invalid settings as errors, the others panic on them.
+pub trait ToPackets {
+    fn try_to_packets_with(
+        &self,
+        builder: &PacketBuilder,
+    ) -> Result<PacketSerializer<'_>, PacketError>;
+
+    fn to_packets(&self, packet_size: u8) -> PacketSerializer<'_> {
+        self.to_packets_with(&PacketBuilder::v1(packet_size))
+    }
 
-impl Packetable for String {
-    fn to_packets(&self, packet_size: u8) -> PacketSerializer {
-        let string_as_bytes = self.as_bytes();
-        PacketSerializer {
-            packet_size,
-            remaining_bytes: string_as_bytes,
-        }
+    fn to_packets_with(&self, builder: &PacketBuilder) -> PacketSerializer<'_> {
+        self.try_to_packets_with(builder).unwrap()
+    }
+
+    fn try_to_packets(&self, packet_size: u8) -> Result<PacketSerializer<'_>, PacketError> {
+        self.try_to_packets_with(&PacketBuilder::v1(packet_size))
     }
 
+    #[cfg(feature = "alloc")]
     fn to_packet_data(&self, packet_size: u8) -> Vec<u8> {
-        let mut serialized_data = Vec::<u8>::new();
-        let packet_serializer = self.to_packets(packet_size);
+        self.to_packet_data_with(&PacketBuilder::v1(packet_size))
+    }
 
-        for packet in packet_serializer {
-            serialized_data.extend(packet.serialize());
-        }
+    #[cfg(feature = "alloc")]
+    fn to_packet_data_with(&self, builder: &PacketBuilder) -> Vec<u8> {
+        self.try_to_packet_data_with(builder).unwrap()
+    }
 
-        serialized_data
+    #[cfg(feature = "alloc")]
+    fn try_to_packet_data(&self, packet_size: u8) -> Result<Vec<u8>, PacketError> {
+        self.try_to_packet_data_with(&PacketBuilder::v1(packet_size))
     }
 
-    fn from_packet_data(packet_data: &[u8]) -> Result<Self, PacketError> {
-        let mut remaining_data: &[u8] = packet_data;
-        let mut encoded_message = Vec::<u8>::new();
+    #[cfg(feature = "alloc")]
+    fn try_to_packet_data_with(&self, builder: &PacketBuilder) -> Result<Vec<u8>, PacketError> {
+        let mut serialized_data = Vec::<u8>::new();
+        self.write_packet_data_with(builder, &mut serialized_data)?;
 
-        while remaining_data.len() > 0 {
-            let (packet, remainder) = Packet::deserialize(remaining_data)?;
+        Ok(serialized_data)
+    }
 
-            encoded_message.extend_from_slice(packet.payload());
-            remaining_data = remainder;
+    /// Appends the serialized packets to `out`, so that a buffer can be reused across messages.
+    ///
+    /// Nothing is written if the settings are invalid.
+    fn write_packet_data_with(
+        &self,
+        builder: &PacketBuilder,
+        out: &mut impl Extend<u8>,
+    ) -> Result<(), PacketError> {
+        let packets = match builder.compression {
+            // Only the compressed message has to fit in the packets of a sequenced message.
+            Some(_) => {
+                builder.validate()?;
+                let unsequenced = PacketBuilder {
+                    message_id: None,
+                    ..*builder
+                };
+                self.try_to_packets_with(&unsequenced)?
+            }
+            None => self.try_to_packets_with(builder)?,
+        };
+
+        #[cfg(feature = "alloc")]
+        if builder.compression.is_some() || builder.parity.is_some() {
+            let compressed;
+            let (message, flags) = match builder.compression {
+                Some(compression) => {
+                    compressed = compression.compress(packets.remaining_bytes)?;
+                    (&compressed[..], COMPRESSED_FLAG)
+                }
+                None => (packets.remaining_bytes, 0),
+            };
+            let mut parity = builder.parity.map(ParityGroup::new);
+
+            for mut packet in builder.try_packets(message)? {
+                if flags != 0 {
+                    packet.flags |= flags;
+                    // A MAC covers the flags as well as the payload.
+                    packet.checksum = builder.checksum_of(&packet);
+                }
+                packet.write_to(out);
+
+                if let Some(parity) = &mut parity {
+                    if parity.push(&packet) {
+                        parity.write_to(builder, out);
+                    }
+                }
+            }
+            if let Some(parity) = &mut parity {
+                parity.write_to(builder, out);
+            }
+            return Ok(());
+        }
+
+        for packet in packets {
+            packet.write_to(out);
         }
 
-        String::from_utf8(encoded_message).map_err(|_| PacketError::CorruptedMessage)
+        Ok(())
+    }
+}
+
+impl ToPackets for [u8] {
+    fn try_to_packets_with(
+        &self,
+        builder: &PacketBuilder,
+    ) -> Result<PacketSerializer<'_>, PacketError> {
+        builder.try_packets(self)
+    }
+}
+
+impl ToPackets for str {
+    fn try_to_packets_with(
+        &self,
+        builder: &PacketBuilder,
+    ) -> Result<PacketSerializer<'_>, PacketError> {
+        let string_as_bytes = self.as_bytes();
+        builder.try_packets(string_as_bytes)
     }
 }
@@ -0,0 +1,115 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+#[cfg(feature = "alloc")]
+use crate::{checksum_field, ChecksumAlgorithm, CHECKSUM_ALGORITHM_MASK};
+use crate::{MacLength, Packet, PacketError, PacketErrorKind, MAX_CHECKSUM_LENGTH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Key for the HMAC-SHA256 tags that replace the checksum on links where integrity matters
+/// against an attacker, not just against noise, see [`PacketBuilder::mac`].
+///
+/// The tag covers the header as well as the payload. It is truncated to the given length,
+/// which is stored in the packet, and packets with a tag of any other length are rejected.
+/// Tags do not hide the payload and do not detect replayed packets.
+///
+/// [`PacketBuilder::mac`]: crate::PacketBuilder::mac
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct MacKey {
+    key: [u8; 32],
+    length: MacLength,
+}
+
+impl MacKey {
+    pub fn new(key: &[u8; 32], length: MacLength) -> Self {
+        MacKey { key: *key, length }
+    }
+
+    pub fn length(&self) -> MacLength {
+        self.length
+    }
+
+    /// Verifies every packet of `packet_data` and returns it with CRC-32 checksums in place of
+    /// the tags, for [`Packetable::from_packet_data`](crate::Packetable::from_packet_data)
+    /// and friends.
+    #[cfg(feature = "alloc")]
+    pub fn open(&self, packet_data: &[u8]) -> Result<Vec<u8>, PacketError> {
+        let mut opened = Vec::with_capacity(packet_data.len());
+        let mut remainder = packet_data;
+        let mut index = 0;
+
+        while !remainder.is_empty() {
+            let offset = packet_data.len() - remainder.len();
+            let (packet, rest) = Packet::deserialize_with_key(remainder, self)
+                .map_err(|error| error.at(offset, index))?;
+
+            let checksum = ChecksumAlgorithm::Crc32;
+            let mut opened_packet = Packet {
+                flags: (packet.flags & !CHECKSUM_ALGORITHM_MASK) | checksum.id(),
+                ..packet
+            };
+            opened_packet.checksum = checksum_field(opened_packet.compute_checksum(checksum));
+            opened_packet.write_to(&mut opened);
+
+            remainder = rest;
+            index += 1;
+        }
+
+        Ok(opened)
+    }
+
+    /// The tag of a packet whose flags already hold the id of this key's length.
+    pub(crate) fn tag(&self, packet: &Packet) -> [u8; MAX_CHECKSUM_LENGTH] {
+        let mut tag = [0; MAX_CHECKSUM_LENGTH];
+        tag.copy_from_slice(&self.hmac(packet).finalize().into_bytes());
+        tag
+    }
+
+    fn hmac(&self, packet: &Packet) -> HmacSha256 {
+        let (header, header_length) = packet.header();
+        let mut hmac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+        hmac.update(&header[..header_length]);
+        hmac.update(packet.payload);
+        hmac
+    }
+}
+
+impl fmt::Debug for MacKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MacKey")
+            .field("length", &self.length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Packet<'a> {
+    /// Like [`Packet::deserialize`], but for packets tagged with `key` instead of checksummed.
+    ///
+    /// Packets without a tag, or with a tag of another length, are rejected with
+    /// [`PacketErrorKind::AuthenticationFailed`] like forged ones.
+    pub fn deserialize_with_key(
+        bytes: &'a [u8],
+        key: &MacKey,
+    ) -> Result<(Packet<'a>, &'a [u8]), PacketError> {
+        let (packet, remainder) = Self::deserialize_unverified(bytes)?;
+        packet.verify_mac(key)?;
+
+        Ok((packet, remainder))
+    }
+
+    pub fn verify_mac(&self, key: &MacKey) -> Result<(), PacketError> {
+        if self.mac_length() != Some(key.length) {
+            return Err(PacketErrorKind::AuthenticationFailed.into());
+        }
+
+        key.hmac(self)
+            .verify_truncated_left(self.checksum())
+            .map_err(|_| PacketErrorKind::AuthenticationFailed.into())
+    }
+}
@@ -1,26 +1,293 @@
-// use solution::Packet;
-// use solution::PacketSerializer;
-// use solution::Packetable;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
 use solution::*;
 
+const USAGE: &str = "\
+Usage: solution <command> [options] [file]
+
+Reads from `file`, or from standard input if it is missing or `-`.
+
+Commands:
+  encode    Split the input into a packet stream
+  decode    Join the payloads of a packet stream
+  inspect   List the packets of a stream, one per line
+  dump      Show every packet of a stream with its payload in hex
+
+Encode options:
+  --packet-size <n>     Payload bytes per packet (default 255)
+  --v1                  Use protocol version 1
+  --checksum <name>     additive, crc32, crc32c or adler32 (default additive)
+  --sequenced <id>      Number the packets with the given message id
+  --framed              Mark the last packet of the message
+  --parity <n>          Follow every n packets with a parity packet that can repair one
+
+Decode options:
+  --skip-damaged        Drop damaged packets instead of stopping at the first one
+  --repair              Rebuild damaged packets from the parity packets of the stream
+";
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
 fn main() {
-    // let (packet, _remainder) = Packet::from_source(b"rbcd", 3);
-    // let serialized = packet.serialize();
-    // println!("{:?}", serialized);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if let Err(error) = run(&args) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> CliResult<()> {
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => return Err(usage_error("missing command")),
+    };
+    let mut options = Options::parse(args)?;
+    let mut stdout = io::stdout();
+
+    match command {
+        "encode" => {
+            let builder = options.builder()?;
+            options.finish()?;
+            let input = read_input(options.file.as_deref())?;
+
+            stdout.write_all(&input.try_to_packet_data_with(&builder)?)?;
+        }
+        "decode" => {
+            let skip_damaged = options.flag("--skip-damaged");
+            let repair = options.flag("--repair");
+            options.finish()?;
+            if skip_damaged && repair {
+                return Err(usage_error(
+                    "--skip-damaged and --repair cannot be used together",
+                ));
+            }
+            let input = read_input(options.file.as_deref())?;
+
+            if skip_damaged {
+                decode_damaged(&input, &mut stdout)?;
+            } else if repair {
+                stdout.write_all(&Vec::<u8>::from_packet_data_with_repair(&input)?)?;
+            } else {
+                stdout.write_all(&Vec::<u8>::from_packet_data(&input)?)?;
+            }
+        }
+        "inspect" => {
+            options.finish()?;
+            let input = read_input(options.file.as_deref())?;
+
+            inspect(&input, &mut stdout)?;
+        }
+        "dump" => {
+            options.finish()?;
+            let input = read_input(options.file.as_deref())?;
+
+            write!(stdout, "{}", StreamDump::new(&input))?;
+        }
+        "help" | "--help" | "-h" => print!("{}", USAGE),
+        _ => return Err(usage_error(&format!("unknown command `{}`", command))),
+    }
+
+    stdout.flush()?;
+    Ok(())
+}
+
+fn usage_error(message: &str) -> Box<dyn Error> {
+    format!("{}\n\n{}", message, USAGE).into()
+}
+
+/// Options of a command, consumed as the command looks them up.
+struct Options {
+    values: Vec<(String, Option<String>)>,
+    file: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> CliResult<Self> {
+        const WITH_VALUE: [&str; 4] = ["--packet-size", "--checksum", "--sequenced", "--parity"];
+
+        let mut values = Vec::new();
+        let mut file = None;
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            if WITH_VALUE.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| usage_error(&format!("missing value for `{}`", arg)))?;
+                values.push((arg.clone(), Some(value.clone())));
+            } else if arg.starts_with("--") {
+                values.push((arg.clone(), None));
+            } else if file.is_none() {
+                file = Some(arg.clone());
+            } else {
+                return Err(usage_error(&format!("unexpected argument `{}`", arg)));
+            }
+        }
+
+        Ok(Options { values, file })
+    }
+
+    fn take(&mut self, name: &str) -> Option<Option<String>> {
+        let position = self.values.iter().position(|(option, _)| option == name)?;
+        Some(self.values.remove(position).1)
+    }
+
+    fn flag(&mut self, name: &str) -> bool {
+        self.take(name).is_some()
+    }
+
+    fn value(&mut self, name: &str) -> Option<String> {
+        self.take(name).flatten()
+    }
+
+    fn builder(&mut self) -> CliResult<PacketBuilder> {
+        let packet_size = match self.value("--packet-size") {
+            Some(size) => size
+                .parse()
+                .map_err(|_| format!("invalid packet size `{}`", size))?,
+            None => 255,
+        };
+        let mut builder = PacketBuilder::new(packet_size);
+
+        if self.flag("--v1") {
+            builder = builder.version(ProtocolVersion::V1);
+        }
+        if let Some(name) = self.value("--checksum") {
+            builder = builder.checksum(parse_checksum(&name)?);
+        }
+        if let Some(id) = self.value("--sequenced") {
+            let id = id
+                .parse()
+                .map_err(|_| format!("invalid message id `{}`", id))?;
+            builder = builder.sequenced(id);
+        }
+        if self.flag("--framed") {
+            builder = builder.framed();
+        }
+        if let Some(group_size) = self.value("--parity") {
+            let group_size = group_size
+                .parse()
+                .map_err(|_| format!("invalid parity group size `{}`", group_size))?;
+            builder = builder.parity(group_size);
+        }
 
-    // let deserialized = Packet::deserialize(&serialized);
-    // println!("{:?}", deserialized);
+        builder.validate()?;
+        Ok(builder)
+    }
+
+    /// Fails if an option was given that the command does not know.
+    fn finish(&self) -> CliResult<()> {
+        match self.values.first() {
+            Some((option, _)) => Err(usage_error(&format!("unknown option `{}`", option))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_checksum(name: &str) -> CliResult<ChecksumAlgorithm> {
+    match name {
+        "additive" => Ok(ChecksumAlgorithm::Additive),
+        "crc32" => Ok(ChecksumAlgorithm::Crc32),
+        "crc32c" => Ok(ChecksumAlgorithm::Crc32c),
+        "adler32" => Ok(ChecksumAlgorithm::Adler32),
+        _ => Err(format!("unknown checksum `{}`", name).into()),
+    }
+}
+
+fn read_input(file: Option<&str>) -> CliResult<Vec<u8>> {
+    match file {
+        None | Some("-") => {
+            let mut input = Vec::new();
+            io::stdin().read_to_end(&mut input)?;
+            Ok(input)
+        }
+        Some(path) => fs::read(path).map_err(|error| format!("{}: {}", path, error).into()),
+    }
+}
+
+/// Writes the payloads of the undamaged packets and reports the rest on stderr.
+///
+/// Sequenced packets are written as they come, without reordering.
+fn decode_damaged(input: &[u8], out: &mut impl Write) -> CliResult<()> {
+    let mut damaged = 0;
+
+    for recovered in Resync::new(input) {
+        match recovered {
+            Recovered::Packet(packet) if packet.is_parity() => {}
+            Recovered::Packet(packet) => out.write_all(packet.payload())?,
+            Recovered::Skipped(skipped) => {
+                damaged += 1;
+                eprintln!(
+                    "skipped bytes {}..{}: {}",
+                    skipped.range.start, skipped.range.end, skipped.error
+                );
+            }
+        }
+    }
+
+    if damaged > 0 {
+        return Err(format!("{} damaged section(s) skipped", damaged).into());
+    }
+    Ok(())
+}
 
-    // let arr = vec![1, 3, 114, 98, 99, 0, 0, 1, 55];
-    // let deserialized_broken = Packet::deserialize(&arr);
-    // println!("{:?}", deserialized_broken);
+fn inspect(input: &[u8], out: &mut impl Write) -> CliResult<()> {
+    writeln!(
+        out,
+        "  OFFSET  VERSION   SIZE  CHECKSUM  SEQUENCE           LAST  VALID"
+    )?;
 
-    // let string = String::from("asdf");
-    // let serializer: Vec<Packet> = string.to_packets(4).collect();
-    // println!("{:?}", serializer);
-    let packet = [1, 0, 0, 0, 0, 0];
-    let content = String::from_packet_data(&packet).unwrap();
+    let mut offset = 0;
+    while offset < input.len() {
+        match Packet::deserialize_unverified(&input[offset..]) {
+            Ok((packet, _)) => {
+                let sequence = match packet.sequence() {
+                    Some(sequence) => format!(
+                        "{}:{}/{}",
+                        sequence.message_id, sequence.index, sequence.count
+                    ),
+                    None => String::from("-"),
+                };
+                let valid = match packet.verify_checksum() {
+                    Ok(()) => String::from("yes"),
+                    Err(error) => format!("no ({})", error),
+                };
+                writeln!(
+                    out,
+                    "{:>8}  {:>7}  {:>5}  {:<8}  {:<17}  {:<4}  {}",
+                    offset,
+                    packet.version(),
+                    packet.payload().len(),
+                    match packet.checksum_algorithm() {
+                        Some(algorithm) => format!("{:?}", algorithm).to_lowercase(),
+                        None => String::from("hmac"),
+                    },
+                    sequence,
+                    if packet.is_last() { "yes" } else { "no" },
+                    valid
+                )?;
+                offset += packet.encoded_len();
+            }
+            Err(error) => {
+                let end = match Resync::new(&input[offset..]).next() {
+                    Some(Recovered::Skipped(skipped)) => offset + skipped.range.end,
+                    _ => input.len(),
+                };
+                writeln!(
+                    out,
+                    "{:>8}  skipped {} bytes: {}",
+                    offset,
+                    end - offset,
+                    error
+                )?;
+                offset = end;
+            }
+        }
+    }
 
-    println!("result {:?}", content);
-    //assert_eq!(content, "");
+    Ok(())
 }
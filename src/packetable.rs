@@ -0,0 +1,280 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::compression::decompress;
+use crate::parity::{has_parity, repair};
+use crate::reassembly::Slots;
+use crate::{
+    Packet, PacketBuilder, PacketError, PacketErrorKind, PacketIter, ToPackets,
+    DEFAULT_DECOMPRESSION_LIMIT,
+};
+
+pub trait Packetable: Sized {
+    fn try_to_packet_data_with(&self, builder: &PacketBuilder) -> Result<Vec<u8>, PacketError>;
+    /// Decodes a message, decompressing it if needed.
+    ///
+    /// Packet data holding several messages decodes to all of them joined together, each
+    /// compressed one being decompressed on its own. Damaged packets are not repaired, see
+    /// [`Packetable::from_packet_data_with_repair`].
+    ///
+    /// Compressed messages that decompress to more than [`DEFAULT_DECOMPRESSION_LIMIT`]
+    /// bytes are rejected, see [`Packetable::from_packet_data_with_limit`].
+    fn from_packet_data(packet_data: &[u8]) -> Result<Self, PacketError>;
+
+    fn to_packet_data(&self, packet_size: u8) -> Vec<u8> {
+        self.to_packet_data_with(&PacketBuilder::v1(packet_size))
+    }
+
+    /// Panics if the builder settings are invalid, see [`PacketBuilder::validate`].
+    fn to_packet_data_with(&self, builder: &PacketBuilder) -> Vec<u8> {
+        self.try_to_packet_data_with(builder).unwrap()
+    }
+
+    fn try_to_packet_data(&self, packet_size: u8) -> Result<Vec<u8>, PacketError> {
+        self.try_to_packet_data_with(&PacketBuilder::v1(packet_size))
+    }
+
+    fn write_packet_data(
+        &self,
+        packet_size: u8,
+        out: &mut impl Extend<u8>,
+    ) -> Result<(), PacketError> {
+        self.write_packet_data_with(&PacketBuilder::v1(packet_size), out)
+    }
+
+    /// Appends the packet data to `out` instead of returning a new buffer.
+    ///
+    /// Nothing is written if the settings are invalid. The default implementation goes through
+    /// [`Packetable::try_to_packet_data_with`]; byte and string types write their packets
+    /// straight into `out`.
+    fn write_packet_data_with(
+        &self,
+        builder: &PacketBuilder,
+        out: &mut impl Extend<u8>,
+    ) -> Result<(), PacketError> {
+        out.extend(self.try_to_packet_data_with(builder)?);
+
+        Ok(())
+    }
+
+    /// Like [`Packetable::from_packet_data`], but with a custom limit on the size of
+    /// decompressed messages, beyond which [`PacketErrorKind::MessageTooLarge`] is returned.
+    ///
+    /// The default implementation ignores `limit`; the provided impls and
+    /// `#[derive(Packetable)]` honour it.
+    fn from_packet_data_with_limit(packet_data: &[u8], limit: usize) -> Result<Self, PacketError> {
+        let _ = limit;
+        Self::from_packet_data(packet_data)
+    }
+
+    /// Like [`Packetable::from_packet_data`], but first rebuilds damaged or lost packets from
+    /// the parity packets of the message, if it has any, see [`PacketBuilder::parity`].
+    ///
+    /// Errors are reported for the data as received, even if repairing it did not help.
+    /// Looking for damaged packets costs more than decoding, so only use this for data from
+    /// links that actually damage it.
+    fn from_packet_data_with_repair(packet_data: &[u8]) -> Result<Self, PacketError> {
+        match Self::from_packet_data(packet_data) {
+            Err(error) if !has_parity(packet_data) => Err(error),
+            Err(error) => match repair(packet_data) {
+                Some(repaired) => Self::from_packet_data(&repaired).map_err(|_| error),
+                None => Err(error),
+            },
+            decoded => decoded,
+        }
+    }
+
+    /// Decodes the first message of a stream and returns it with the rest of the stream.
+    ///
+    /// A message ends with a packet marked as last (see [`PacketBuilder::framed`]) or, for
+    /// sequenced packets, once all of its packets have been read. Running out of data before
+    /// that is reported as [`PacketErrorKind::IncompleteMessage`].
+    fn next_message(packet_data: &[u8]) -> Result<(Self, &[u8]), PacketError> {
+        let length = message_length(packet_data)?;
+        let message = Self::from_packet_data(&packet_data[..length])?;
+
+        Ok((message, &packet_data[length..]))
+    }
+}
+
+impl Packetable for String {
+    fn try_to_packet_data_with(&self, builder: &PacketBuilder) -> Result<Vec<u8>, PacketError> {
+        ToPackets::try_to_packet_data_with(self.as_str(), builder)
+    }
+
+    fn write_packet_data_with(
+        &self,
+        builder: &PacketBuilder,
+        out: &mut impl Extend<u8>,
+    ) -> Result<(), PacketError> {
+        ToPackets::write_packet_data_with(self.as_str(), builder, out)
+    }
+
+    fn from_packet_data(packet_data: &[u8]) -> Result<Self, PacketError> {
+        Self::from_packet_data_with_limit(packet_data, DEFAULT_DECOMPRESSION_LIMIT)
+    }
+
+    fn from_packet_data_with_limit(packet_data: &[u8], limit: usize) -> Result<Self, PacketError> {
+        let encoded_message = join_payloads(packet_data, limit)?;
+
+        Ok(String::from_utf8(encoded_message)?)
+    }
+}
+
+impl Packetable for Vec<u8> {
+    fn try_to_packet_data_with(&self, builder: &PacketBuilder) -> Result<Vec<u8>, PacketError> {
+        ToPackets::try_to_packet_data_with(self.as_slice(), builder)
+    }
+
+    fn write_packet_data_with(
+        &self,
+        builder: &PacketBuilder,
+        out: &mut impl Extend<u8>,
+    ) -> Result<(), PacketError> {
+        ToPackets::write_packet_data_with(self.as_slice(), builder, out)
+    }
+
+    fn from_packet_data(packet_data: &[u8]) -> Result<Self, PacketError> {
+        join_payloads(packet_data, DEFAULT_DECOMPRESSION_LIMIT)
+    }
+
+    fn from_packet_data_with_limit(packet_data: &[u8], limit: usize) -> Result<Self, PacketError> {
+        join_payloads(packet_data, limit)
+    }
+}
+
+impl Packetable for Box<[u8]> {
+    fn try_to_packet_data_with(&self, builder: &PacketBuilder) -> Result<Vec<u8>, PacketError> {
+        ToPackets::try_to_packet_data_with(&self[..], builder)
+    }
+
+    fn write_packet_data_with(
+        &self,
+        builder: &PacketBuilder,
+        out: &mut impl Extend<u8>,
+    ) -> Result<(), PacketError> {
+        ToPackets::write_packet_data_with(&self[..], builder, out)
+    }
+
+    fn from_packet_data(packet_data: &[u8]) -> Result<Self, PacketError> {
+        join_payloads(packet_data, DEFAULT_DECOMPRESSION_LIMIT).map(Vec::into_boxed_slice)
+    }
+
+    fn from_packet_data_with_limit(packet_data: &[u8], limit: usize) -> Result<Self, PacketError> {
+        join_payloads(packet_data, limit).map(Vec::into_boxed_slice)
+    }
+}
+
+/// Decodes a text message, replacing invalid UTF-8 with `U+FFFD` instead of failing.
+///
+/// Also returns the byte ranges of the message that were replaced, so that messages from a
+/// peer using another encoding can be told apart from corrupted ones.
+pub fn from_packet_data_lossy(
+    packet_data: &[u8],
+) -> Result<(String, Vec<Range<usize>>), PacketError> {
+    let encoded_message = join_payloads(packet_data, DEFAULT_DECOMPRESSION_LIMIT)?;
+    let mut message = String::with_capacity(encoded_message.len());
+    let mut invalid_ranges = Vec::new();
+    let mut offset = 0;
+
+    for chunk in encoded_message.utf8_chunks() {
+        message.push_str(chunk.valid());
+        offset += chunk.valid().len();
+
+        if !chunk.invalid().is_empty() {
+            message.push(char::REPLACEMENT_CHARACTER);
+            invalid_ranges.push(offset..(offset + chunk.invalid().len()));
+            offset += chunk.invalid().len();
+        }
+    }
+
+    Ok((message, invalid_ranges))
+}
+
+fn message_length(packet_data: &[u8]) -> Result<usize, PacketError> {
+    let mut remaining_data: &[u8] = packet_data;
+    // Only which packets have arrived matters, not their payloads.
+    let mut slots = Slots::default();
+    let mut index = 0;
+
+    loop {
+        let offset = packet_data.len() - remaining_data.len();
+        let locate = |error: PacketError| error.at(offset, index);
+
+        if remaining_data.is_empty() {
+            return Err(locate(PacketErrorKind::IncompleteMessage.into()));
+        }
+        let (packet, remainder) = Packet::deserialize(remaining_data).map_err(locate)?;
+        remaining_data = remainder;
+
+        let complete = match packet.sequence() {
+            _ if packet.is_parity() => false,
+            Some(sequence) => slots.push(sequence, ()).map_err(locate)?.is_some(),
+            None => packet.is_last(),
+        };
+        if complete {
+            // The parity packet of the last group follows the end of the message.
+            while let Ok((packet, remainder)) = Packet::deserialize(remaining_data) {
+                if !packet.is_parity() {
+                    break;
+                }
+                remaining_data = remainder;
+            }
+            return Ok(packet_data.len() - remaining_data.len());
+        }
+        index += 1;
+    }
+}
+
+fn join_payloads(packet_data: &[u8], limit: usize) -> Result<Vec<u8>, PacketError> {
+    let chunks = PacketIter::new(packet_data).payload_chunks()?;
+    if chunks.is_compressed() {
+        return decompress_messages(packet_data, limit);
+    }
+    let mut encoded_message = Vec::with_capacity(chunks.remaining());
+
+    for chunk in chunks {
+        encoded_message.extend_from_slice(chunk);
+    }
+
+    Ok(encoded_message)
+}
+
+/// Decompresses every message of already verified packet data and joins them.
+///
+/// Each message was compressed on its own, so they are told apart as with
+/// [`Packetable::next_message`]. Errors are located at the first packet of their message.
+fn decompress_messages(packet_data: &[u8], limit: usize) -> Result<Vec<u8>, PacketError> {
+    let mut joined = Vec::new();
+    let mut remaining_data = packet_data;
+    let mut index = 0;
+
+    while !remaining_data.is_empty() {
+        let offset = packet_data.len() - remaining_data.len();
+        // Only an unterminated message can fail here, which runs to the end of the data.
+        let length = message_length(remaining_data).unwrap_or(remaining_data.len());
+        let (message_data, remainder) = remaining_data.split_at(length);
+
+        let chunks = PacketIter::new(message_data).payload_chunks()?;
+        let mut encoded_message = Vec::with_capacity(chunks.remaining());
+        for chunk in chunks {
+            encoded_message.extend_from_slice(chunk);
+        }
+        let message = decompress(&encoded_message, limit - joined.len())
+            .map_err(|error| match error.kind() {
+                PacketErrorKind::MessageTooLarge { .. } => {
+                    PacketErrorKind::MessageTooLarge { limit }.into()
+                }
+                _ => error,
+            })
+            .map_err(|error| error.at(offset, index))?;
+        joined.extend(message);
+
+        remaining_data = remainder;
+        index += PacketIter::new(message_data).count();
+    }
+
+    Ok(joined)
+}
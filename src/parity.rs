@@ -0,0 +1,337 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::reassembly::SEQUENCE_LENGTH;
+#[cfg(feature = "alloc")]
+use crate::{
+    checksum_field, Packet, PacketBuilder, PacketError, Recovered, Sequence, Skipped,
+    MAX_CHECKSUM_LENGTH, PARITY_FLAG, SEQUENCED_FLAG,
+};
+
+/// The flags, size and sequence of a data packet, in front of its payload.
+const RECORD_HEADER_LENGTH: usize = 1 + 2 + SEQUENCE_LENGTH;
+
+/// How many times over [`recover`] checksums the data before giving up.
+#[cfg(feature = "alloc")]
+const CHECKSUM_BUDGET: usize = 4;
+
+/// How much larger a parity packet can be than the packets it covers.
+pub(crate) const PARITY_OVERHEAD: usize = 1 + RECORD_HEADER_LENGTH;
+
+/// The XOR of the data packets written since the last parity packet.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub(crate) struct ParityGroup {
+    size: u8,
+    count: u8,
+    parity: Vec<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl ParityGroup {
+    pub(crate) fn new(size: u8) -> Self {
+        ParityGroup {
+            size,
+            count: 0,
+            parity: Vec::new(),
+        }
+    }
+
+    /// Adds a data packet to the group, returning `true` once the group is full.
+    pub(crate) fn push(&mut self, packet: &Packet) -> bool {
+        xor_record(&mut self.parity, packet);
+        self.count += 1;
+
+        self.count == self.size
+    }
+
+    /// Writes the parity packet of the group, if it has any packets, and starts a new one.
+    pub(crate) fn write_to(&mut self, builder: &PacketBuilder, out: &mut impl Extend<u8>) {
+        if self.count == 0 {
+            return;
+        }
+
+        let mut payload = Vec::with_capacity(1 + self.parity.len());
+        payload.push(self.count);
+        payload.extend_from_slice(&self.parity);
+        builder.parity_packet(&payload).write_to(out);
+
+        self.count = 0;
+        self.parity.clear();
+    }
+}
+
+/// XORs the record of `packet` into `parity`, growing it to fit.
+#[cfg(feature = "alloc")]
+fn xor_record(parity: &mut Vec<u8>, packet: &Packet) {
+    let mut header = [0; RECORD_HEADER_LENGTH];
+    header[0] = packet.flags;
+    header[1..3].copy_from_slice(&packet.size.to_be_bytes());
+    if let Some(sequence) = packet.sequence {
+        header[3..].copy_from_slice(&sequence.to_bytes());
+    }
+
+    let length = RECORD_HEADER_LENGTH + packet.payload.len();
+    if parity.len() < length {
+        parity.resize(length, 0);
+    }
+    for (parity, byte) in parity.iter_mut().zip(header.iter().chain(packet.payload)) {
+        *parity ^= byte;
+    }
+}
+
+/// Rebuilds every damaged or lost packet of `packet_data` that is the only one in its parity
+/// group, or returns `None` if there was none.
+///
+/// A packet counts as damaged whether its checksum no longer matches or its header is
+/// unreadable, see [`recover`]. Lost packets are only rebuilt if
+/// they are sequenced, as nothing else tells where they belong; they are put at the end of
+/// their group for reassembly to sort out. Packets that cannot be rebuilt are kept as they
+/// are, so that decoding the result reports them again.
+#[cfg(feature = "alloc")]
+pub(crate) fn repair(packet_data: &[u8]) -> Option<Vec<u8>> {
+    let mut repaired = Vec::with_capacity(packet_data.len());
+    let mut group = Vec::new();
+    let mut rebuilt_any = false;
+
+    for recovered in recover(packet_data)? {
+        let parity = match recovered {
+            Recovered::Packet(packet) if packet.is_parity() => packet,
+            // Nothing depends on a damaged parity packet, so it can simply be left out, as
+            // long as it is not a data packet damaged into looking like one.
+            Recovered::Skipped(skipped)
+                if covers_intact_group(&packet_data[skipped.range.clone()], &group) =>
+            {
+                write_group(packet_data, &group, None, &mut repaired);
+                group.clear();
+                rebuilt_any = true;
+                continue;
+            }
+            recovered => {
+                group.push(recovered);
+                continue;
+            }
+        };
+
+        let rebuilt = rebuild(&group, &parity);
+        rebuilt_any |= rebuilt.is_some();
+        write_group(packet_data, &group, rebuilt, &mut repaired);
+        parity.write_to(&mut repaired);
+        group.clear();
+    }
+    write_group(packet_data, &group, None, &mut repaired);
+
+    if rebuilt_any {
+        Some(repaired)
+    } else {
+        None
+    }
+}
+
+/// Whether `packet_data` could hold a parity packet to repair it with.
+///
+/// Packets are first followed by their headers, which gets past damaged payloads and lost
+/// packets. Past a damaged header, any parity header that is followed by another header or
+/// the end of the data counts. No checksums are computed, as doing so at every offset of
+/// random bytes takes quadratic time.
+#[cfg(feature = "alloc")]
+pub(crate) fn has_parity(packet_data: &[u8]) -> bool {
+    let mut remainder = packet_data;
+    while let Ok((packet, rest)) = Packet::deserialize_unverified(remainder) {
+        if packet.is_parity() {
+            return true;
+        }
+        remainder = rest;
+    }
+
+    (0..packet_data.len()).any(|start| {
+        let bytes = &packet_data[start..];
+        matches!(bytes.get(1), Some(flags) if flags & PARITY_FLAG != 0)
+            && matches!(
+                Packet::deserialize_unverified(bytes),
+                Ok((packet, rest)) if packet.is_parity()
+                    && (rest.is_empty() || Packet::deserialize_unverified(rest).is_ok())
+            )
+    })
+}
+
+/// Splits `packet_data` into packets and runs of damaged bytes.
+///
+/// Unlike [`Resync`](crate::Resync), a damaged packet is skipped as a whole if its header
+/// still leads to a valid packet, or to the end of the data when scanning for the next packet
+/// finds nothing that leads there, as its payload could well hold bytes that look like a
+/// packet. Checksums are only computed where a header fits, and `None` is returned once they
+/// cover the data [`CHECKSUM_BUDGET`] times over, which only crafted input gets near.
+#[cfg(feature = "alloc")]
+fn recover(packet_data: &[u8]) -> Option<Vec<Recovered<'_>>> {
+    let mut recovered = Vec::new();
+    let mut position = 0;
+    let mut budget = CHECKSUM_BUDGET * packet_data.len();
+
+    while position < packet_data.len() {
+        let bytes = &packet_data[position..];
+        let error = match verify(bytes, &mut budget)? {
+            Ok((packet, remainder)) => {
+                recovered.push(Recovered::Packet(packet));
+                position = packet_data.len() - remainder.len();
+                continue;
+            }
+            Err(error) => error,
+        };
+
+        let mut scanned = bytes.len();
+        for start in 1..bytes.len() {
+            if verify(&bytes[start..], &mut budget)?.is_ok() {
+                scanned = start;
+                break;
+            }
+        }
+        let declared = Packet::deserialize_unverified(bytes)
+            .map(|(_, remainder)| bytes.len() - remainder.len())
+            .ok();
+        let length = match declared {
+            Some(end) if end < bytes.len() && verify(&bytes[end..], &mut budget)?.is_ok() => end,
+            // A damaged size can just as well point at the end of the data.
+            Some(end) if end == bytes.len() && !parses_to_end(&bytes[scanned..], &mut budget)? => {
+                end
+            }
+            _ => scanned,
+        };
+        recovered.push(Recovered::Skipped(Skipped {
+            range: position..(position + length),
+            error,
+        }));
+        position += length;
+    }
+
+    Some(recovered)
+}
+
+/// Deserializes the packet at the start of `bytes`, or returns `None` if checking it would
+/// use up more of `budget` than is left.
+#[cfg(feature = "alloc")]
+fn verify<'a>(
+    bytes: &'a [u8],
+    budget: &mut usize,
+) -> Option<Result<(Packet<'a>, &'a [u8]), PacketError>> {
+    let length = match Packet::deserialize_unverified(bytes) {
+        Ok((_, remainder)) => bytes.len() - remainder.len(),
+        Err(error) => return Some(Err(error)),
+    };
+    *budget = budget.checked_sub(length)?;
+
+    Some(Packet::deserialize(bytes))
+}
+
+#[cfg(feature = "alloc")]
+fn parses_to_end(mut bytes: &[u8], budget: &mut usize) -> Option<bool> {
+    while !bytes.is_empty() {
+        match verify(bytes, budget)? {
+            Ok((_, remainder)) => bytes = remainder,
+            Err(_) => return Some(false),
+        }
+    }
+
+    Some(true)
+}
+
+/// Whether `bytes` are exactly one parity packet, its checksum aside, for the packets of
+/// `group`, all of which are intact.
+///
+/// Its length must match the packets, and either its count or its parity as well, as damage
+/// to one still leaves the other to check.
+#[cfg(feature = "alloc")]
+fn covers_intact_group(bytes: &[u8], group: &[Recovered]) -> bool {
+    let parity = match Packet::deserialize_unverified(bytes) {
+        Ok((packet, remainder)) if packet.is_parity() && remainder.is_empty() => packet,
+        _ => return false,
+    };
+    let mut record = Vec::new();
+    for recovered in group {
+        match recovered {
+            Recovered::Packet(packet) => xor_record(&mut record, packet),
+            Recovered::Skipped(_) => return false,
+        }
+    }
+
+    match parity.payload.split_first() {
+        Some((&count, xor)) if !group.is_empty() && xor.len() == record.len() => {
+            count as usize == group.len() || xor == &record[..]
+        }
+        _ => false,
+    }
+}
+
+/// Rebuilds the only damaged or lost packet among the ones covered by `parity`, the last
+/// ones of `group`, returning its position in `group` and its serialized bytes.
+#[cfg(feature = "alloc")]
+fn rebuild(group: &[Recovered], parity: &Packet) -> Option<(usize, Vec<u8>)> {
+    let (&count, xor) = parity.payload.split_first()?;
+    let found = group.len().min(count as usize);
+    let mut record = xor.to_vec();
+    let mut damaged = None;
+
+    for (index, recovered) in group.iter().enumerate().skip(group.len() - found) {
+        match recovered {
+            Recovered::Packet(packet) => xor_record(&mut record, packet),
+            Recovered::Skipped(_) if damaged.is_none() => damaged = Some(index),
+            Recovered::Skipped(_) => return None,
+        }
+    }
+    let position = match (damaged, count as usize - found) {
+        (Some(index), 0) => index,
+        (None, 1) => group.len(),
+        _ => return None,
+    };
+
+    if record.len() < RECORD_HEADER_LENGTH {
+        return None;
+    }
+    let (header, payload) = record.split_at(RECORD_HEADER_LENGTH);
+    let flags = header[0];
+    let size = u16::from_be_bytes([header[1], header[2]]);
+    let mut packet = Packet {
+        version: parity.version,
+        flags,
+        size,
+        sequence: match flags & SEQUENCED_FLAG {
+            0 => None,
+            _ => Some(Sequence::from_bytes(&header[3..])),
+        },
+        payload: payload.get(..size as usize)?,
+        checksum: [0; MAX_CHECKSUM_LENGTH],
+    };
+    // Packets carrying a MAC cannot be rebuilt without the key.
+    let algorithm = packet.checksum_algorithm()?;
+    packet.checksum = checksum_field(packet.compute_checksum(algorithm));
+    if position == group.len() && packet.sequence.is_none() {
+        return None;
+    }
+
+    Some((position, packet.serialize()))
+}
+
+#[cfg(feature = "alloc")]
+fn write_group(
+    packet_data: &[u8],
+    group: &[Recovered],
+    rebuilt: Option<(usize, Vec<u8>)>,
+    out: &mut Vec<u8>,
+) {
+    for (index, recovered) in group.iter().enumerate() {
+        match (recovered, &rebuilt) {
+            (_, Some((rebuilt_index, packet))) if *rebuilt_index == index => {
+                out.extend_from_slice(packet)
+            }
+            (Recovered::Packet(packet), _) => packet.write_to(out),
+            (Recovered::Skipped(skipped), _) => {
+                out.extend_from_slice(&packet_data[skipped.range.clone()])
+            }
+        }
+    }
+    if let Some((index, packet)) = rebuilt {
+        if index == group.len() {
+            out.extend_from_slice(&packet);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+#[cfg(feature = "alloc")]
+use alloc::{
+    collections::{BTreeMap, VecDeque},
+    vec::Vec,
+};
+
+#[cfg(feature = "alloc")]
+use crate::{Packet, PacketError, PacketErrorKind, CHECKSUM_LENGTH, MAX_CHECKSUM_LENGTH};
+
+pub(crate) const SEQUENCE_LENGTH: usize = 6;
+
+/// The shortest a sequenced packet can be: a version 2 header, its sequence and a checksum.
+#[cfg(feature = "alloc")]
+pub(crate) const MIN_SEQUENCED_PACKET_LENGTH: usize = 4 + SEQUENCE_LENGTH + CHECKSUM_LENGTH;
+
+/// How many messages can be started but not completed at a time, see [`Reassembler`].
+pub const MAX_PENDING_MESSAGES: usize = 256;
+
+/// Position of a packet within its message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Sequence {
+    pub message_id: u16,
+    pub index: u16,
+    pub count: u16,
+}
+
+impl Sequence {
+    pub(crate) fn to_bytes(self) -> [u8; SEQUENCE_LENGTH] {
+        let mut bytes = [0; SEQUENCE_LENGTH];
+        bytes[0..2].copy_from_slice(&self.message_id.to_be_bytes());
+        bytes[2..4].copy_from_slice(&self.index.to_be_bytes());
+        bytes[4..6].copy_from_slice(&self.count.to_be_bytes());
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        Sequence {
+            message_id: u16::from_be_bytes([bytes[0], bytes[1]]),
+            index: u16::from_be_bytes([bytes[2], bytes[3]]),
+            count: u16::from_be_bytes([bytes[4], bytes[5]]),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+struct PartialMessage<T> {
+    // Filled as packets arrive, as the count comes from the wire.
+    payloads: BTreeMap<u16, T>,
+    count: u16,
+}
+
+#[cfg(feature = "alloc")]
+/// Bookkeeping of [`Reassembler`], generic over how payloads are held so that borrowed
+/// payloads can be put in order without copying them.
+#[derive(Debug)]
+pub(crate) struct Slots<T> {
+    messages: BTreeMap<u16, PartialMessage<T>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Default for Slots<T> {
+    fn default() -> Self {
+        Slots {
+            messages: BTreeMap::new(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Slots<T> {
+    /// Stores a payload, returning all payloads of its message in order once it is complete.
+    pub(crate) fn push(
+        &mut self,
+        sequence: Sequence,
+        payload: T,
+    ) -> Result<Option<Vec<T>>, PacketError> {
+        if sequence.index >= sequence.count {
+            return Err(PacketErrorKind::InvalidSequence.into());
+        }
+        if !self.messages.contains_key(&sequence.message_id)
+            && self.messages.len() >= MAX_PENDING_MESSAGES
+        {
+            return Err(PacketErrorKind::TooManyPendingMessages.into());
+        }
+
+        let message = self
+            .messages
+            .entry(sequence.message_id)
+            .or_insert_with(|| PartialMessage {
+                payloads: BTreeMap::new(),
+                count: sequence.count,
+            });
+        if message.count != sequence.count {
+            return Err(PacketErrorKind::InvalidSequence.into());
+        }
+        if message.payloads.contains_key(&sequence.index) {
+            return Err(PacketErrorKind::DuplicatePacket.into());
+        }
+
+        message.payloads.insert(sequence.index, payload);
+        if message.payloads.len() < message.count as usize {
+            return Ok(None);
+        }
+
+        let message = self.messages.remove(&sequence.message_id).unwrap();
+        Ok(Some(message.payloads.into_values().collect()))
+    }
+
+    /// How many packets of the message of `sequence` are still missing, not counting the
+    /// packet of `sequence` itself.
+    pub(crate) fn outstanding(&self, sequence: Sequence) -> usize {
+        let received = match self.messages.get(&sequence.message_id) {
+            Some(message) => message.payloads.len(),
+            None => 0,
+        };
+
+        (sequence.count as usize).saturating_sub(received + 1)
+    }
+
+    pub(crate) fn pending(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub(crate) fn is_pending(&self, message_id: u16) -> bool {
+        self.messages.contains_key(&message_id)
+    }
+
+    pub(crate) fn discard(&mut self, message_id: u16) -> bool {
+        self.messages.remove(&message_id).is_some()
+    }
+}
+
+#[cfg(feature = "alloc")]
+/// Collects sequenced packets that may arrive in any order and puts their messages back together.
+///
+/// A message is returned as soon as its last missing packet arrives. The packets of the last
+/// [`MAX_PENDING_MESSAGES`] completed messages are remembered, so that copies of them arriving
+/// late are rejected instead of starting a new message. Any other packet with the id of a
+/// completed message starts a new one, so ids can be reused as long as the messages differ.
+///
+/// At most [`MAX_PENDING_MESSAGES`] messages can be pending at a time, packets starting any
+/// more are rejected until some are completed or [discarded](Reassembler::discard).
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    slots: Slots<([u8; MAX_CHECKSUM_LENGTH], Vec<u8>)>,
+    // The checksums of the packets of recently completed messages, oldest first.
+    completed: VecDeque<(u16, Vec<[u8; MAX_CHECKSUM_LENGTH]>)>,
+}
+
+#[cfg(feature = "alloc")]
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a packet, returning the whole message once every packet of it has been pushed.
+    ///
+    /// Fails with [`PacketErrorKind::DuplicatePacket`] for a packet that was already pushed, and
+    /// with [`PacketErrorKind::InvalidSequence`] if it disagrees with earlier packets of the same
+    /// message about the packet count, and with [`PacketErrorKind::TooManyPendingMessages`]
+    /// if it starts a message while [`MAX_PENDING_MESSAGES`] are pending. Compressed packets
+    /// are rejected with [`PacketErrorKind::CompressedPacket`], as their messages have to be
+    /// decoded with [`Packetable::from_packet_data`](crate::Packetable::from_packet_data).
+    pub fn push(&mut self, packet: &Packet) -> Result<Option<Vec<u8>>, PacketError> {
+        let sequence = packet.sequence().ok_or(PacketErrorKind::MissingSequence)?;
+        if packet.is_compressed() {
+            return Err(PacketErrorKind::CompressedPacket.into());
+        }
+        if !self.slots.is_pending(sequence.message_id) && self.was_completed(packet, sequence) {
+            return Err(PacketErrorKind::DuplicatePacket.into());
+        }
+        let packets = match self
+            .slots
+            .push(sequence, (packet.checksum, packet.payload().to_vec()))?
+        {
+            Some(packets) => packets,
+            None => return Ok(None),
+        };
+
+        let (checksums, payloads): (Vec<_>, Vec<_>) = packets.into_iter().unzip();
+        self.completed
+            .retain(|(message_id, _)| *message_id != sequence.message_id);
+        if self.completed.len() >= MAX_PENDING_MESSAGES {
+            self.completed.pop_front();
+        }
+        self.completed.push_back((sequence.message_id, checksums));
+
+        Ok(Some(payloads.concat()))
+    }
+
+    /// Whether `packet` is one of the packets of a recently completed message.
+    fn was_completed(&self, packet: &Packet, sequence: Sequence) -> bool {
+        self.completed.iter().any(|(message_id, checksums)| {
+            *message_id == sequence.message_id
+                && checksums.len() == sequence.count as usize
+                && checksums.get(sequence.index as usize) == Some(&packet.checksum)
+        })
+    }
+
+    /// Drops the packets received so far for a pending message, returning whether there were
+    /// any.
+    ///
+    /// Messages whose remaining packets were lost otherwise stay pending forever.
+    pub fn discard(&mut self, message_id: u16) -> bool {
+        self.slots.discard(message_id)
+    }
+
+    /// Indices of the packets of a message that have not arrived yet.
+    ///
+    /// Returns an empty list for messages that have not been started or are already complete.
+    pub fn missing(&self, message_id: u16) -> Vec<u16> {
+        match self.slots.messages.get(&message_id) {
+            Some(message) => (0..message.count)
+                .filter(|index| !message.payloads.contains_key(index))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Number of messages that have been started but not completed.
+    pub fn pending(&self) -> usize {
+        self.slots.pending()
+    }
+}
@@ -0,0 +1,69 @@
+use core::ops::Range;
+
+use crate::{Packet, PacketError};
+
+/// A run of bytes that was dropped while resynchronizing, with the error that caused it.
+#[derive(Debug, PartialEq)]
+pub struct Skipped {
+    pub range: Range<usize>,
+    pub error: PacketError,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Recovered<'a> {
+    Packet(Packet<'a>),
+    Skipped(Skipped),
+}
+
+/// Decodes a packet stream, skipping over damaged packets instead of stopping at the first one.
+///
+/// After an error the stream is scanned byte by byte for the next offset holding a whole
+/// valid packet (known version, consistent length and matching checksum), and decoding
+/// continues from there. Everything in between is reported as [`Recovered::Skipped`].
+#[derive(Debug)]
+pub struct Resync<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    packets: usize,
+}
+
+impl<'a> Resync<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Resync {
+            bytes,
+            position: 0,
+            packets: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Resync<'a> {
+    type Item = Recovered<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let byte_count = self.bytes.len();
+        if self.position >= byte_count {
+            return None;
+        }
+
+        match Packet::deserialize(&self.bytes[self.position..]) {
+            Ok((packet, remainder)) => {
+                self.position = byte_count - remainder.len();
+                self.packets += 1;
+                Some(Recovered::Packet(packet))
+            }
+            Err(error) => {
+                let start = self.position;
+                let end = ((start + 1)..byte_count)
+                    .find(|&offset| Packet::deserialize(&self.bytes[offset..]).is_ok())
+                    .unwrap_or(byte_count);
+                self.position = end;
+
+                Some(Recovered::Skipped(Skipped {
+                    range: start..end,
+                    error: error.at(start, self.packets),
+                }))
+            }
+        }
+    }
+}
@@ -1,3 +1,9 @@
+#![allow(
+    clippy::assertions_on_constants,
+    clippy::len_zero,
+    clippy::redundant_pattern_matching
+)]
+
 // Бележка: името на проекта трябва да се казва "solution". Ако не се казва така, променете го
 // на този ред:
 use solution::*;
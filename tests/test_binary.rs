@@ -0,0 +1,37 @@
+use solution::*;
+
+#[test]
+fn test_binary_round_trip() {
+    let initial_data: Vec<u8> = (0..=255).rev().collect();
+    let packet_data = initial_data.to_packet_data(7);
+
+    assert!(String::from_packet_data(&packet_data).is_err());
+    assert_eq!(
+        Vec::<u8>::from_packet_data(&packet_data).unwrap(),
+        initial_data
+    );
+}
+
+#[test]
+fn test_boxed_round_trip() {
+    let initial_data: Box<[u8]> = vec![0xFF, 0x00, 0xC3, 0x28].into_boxed_slice();
+    let packet_data = initial_data.to_packet_data_with(&PacketBuilder::new(3));
+
+    assert_eq!(
+        Box::<[u8]>::from_packet_data(&packet_data).unwrap(),
+        initial_data
+    );
+}
+
+#[test]
+fn test_borrowed_sources() {
+    let bytes: &[u8] = b"borrowed";
+    let text = "borrowed";
+
+    assert_eq!(bytes.to_packet_data(3), text.to_packet_data(3));
+    assert_eq!(bytes.to_packets(3).count(), 3);
+    assert_eq!(
+        Vec::<u8>::from_packet_data(&bytes.to_packet_data(3)).unwrap(),
+        bytes
+    );
+}
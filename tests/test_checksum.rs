@@ -0,0 +1,68 @@
+use solution::*;
+
+#[test]
+fn test_known_checksums() {
+    assert_eq!(Additive.checksum(b"123456789"), 477u32.to_be_bytes());
+    assert_eq!(Crc32.checksum(b"123456789"), 0xCBF4_3926u32.to_be_bytes());
+    assert_eq!(Crc32c.checksum(b"123456789"), 0xE306_9283u32.to_be_bytes());
+    assert_eq!(Adler32.checksum(b"Wikipedia"), 0x11E6_0398u32.to_be_bytes());
+}
+
+#[test]
+fn test_reordered_bytes() {
+    assert_eq!(Additive.checksum(b"ab"), Additive.checksum(b"ba"));
+    assert_ne!(Crc32.checksum(b"ab"), Crc32.checksum(b"ba"));
+    assert_ne!(Crc32c.checksum(b"ab"), Crc32c.checksum(b"ba"));
+    assert_ne!(Adler32.checksum(b"ab"), Adler32.checksum(b"ba"));
+}
+
+#[test]
+fn test_round_trip_with_each_algorithm() {
+    let algorithms = [
+        ChecksumAlgorithm::Additive,
+        ChecksumAlgorithm::Crc32,
+        ChecksumAlgorithm::Crc32c,
+        ChecksumAlgorithm::Adler32,
+    ];
+
+    for &algorithm in algorithms.iter() {
+        let initial_data = String::from("checksummed message");
+        let builder = PacketBuilder::new(5).checksum(algorithm);
+        let packet_data = initial_data.to_packet_data_with(&builder);
+
+        let (packet, _) = Packet::deserialize(&packet_data).unwrap();
+        assert_eq!(packet.checksum_algorithm(), Some(algorithm));
+        assert_eq!(
+            String::from_packet_data(&packet_data).unwrap(),
+            initial_data
+        );
+    }
+}
+
+#[test]
+fn test_swapped_bytes_detected() {
+    let builder = PacketBuilder::new(10).checksum(ChecksumAlgorithm::Crc32);
+    let mut packet_data = String::from("ab").to_packet_data_with(&builder);
+    packet_data.swap(4, 5);
+
+    let error = String::from_packet_data(&packet_data).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+}
+
+#[test]
+fn test_unknown_checksum_algorithm() {
+    assert_eq!(
+        ChecksumAlgorithm::from_id(4),
+        Err(PacketErrorKind::UnknownChecksumAlgorithm(4).into())
+    );
+
+    // The remaining ids stand for MACs, which take a key to verify.
+    let mut packet_data = String::from("abcd").to_packet_data_with(&PacketBuilder::new(10));
+    packet_data[1] = MacLength::Bytes4.id();
+
+    let error = String::from_packet_data(&packet_data).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::MissingKey);
+}
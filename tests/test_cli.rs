@@ -0,0 +1,125 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use solution::*;
+
+fn run(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_solution"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_encode_decode_round_trip() {
+    let encoded = run(
+        &[
+            "encode",
+            "--packet-size",
+            "4",
+            "--checksum",
+            "crc32c",
+            "--framed",
+        ],
+        b"round trip",
+    );
+    assert!(encoded.status.success());
+
+    let builder = PacketBuilder::new(4)
+        .checksum(ChecksumAlgorithm::Crc32c)
+        .framed();
+    assert_eq!(encoded.stdout, "round trip".to_packet_data_with(&builder));
+
+    let decoded = run(&["decode"], &encoded.stdout);
+    assert!(decoded.status.success());
+    assert_eq!(decoded.stdout, b"round trip");
+}
+
+#[test]
+fn test_decode_reports_errors() {
+    let mut packet_data = "abcdefgh".to_packet_data(4);
+    packet_data[13] ^= 1;
+
+    let decoded = run(&["decode", "-"], &packet_data);
+    assert!(!decoded.status.success());
+    let stderr = String::from_utf8(decoded.stderr).unwrap();
+    assert!(stderr.contains("in packet 1 at byte 10"), "{}", stderr);
+
+    let decoded = run(&["decode", "--skip-damaged"], &packet_data);
+    assert!(!decoded.status.success());
+    assert_eq!(decoded.stdout, b"abcd");
+    let stderr = String::from_utf8(decoded.stderr).unwrap();
+    assert!(stderr.contains("skipped bytes 10..20"), "{}", stderr);
+}
+
+#[test]
+fn test_decode_repairs_with_parity() {
+    let encoded = run(
+        &[
+            "encode",
+            "--packet-size",
+            "4",
+            "--checksum",
+            "crc32",
+            "--parity",
+            "2",
+        ],
+        b"repaired on the way",
+    );
+    assert!(encoded.status.success());
+
+    let mut damaged = encoded.stdout;
+    damaged[5] ^= 1;
+    assert!(!run(&["decode"], &damaged).status.success());
+    let decoded = run(&["decode", "--repair"], &damaged);
+    assert!(decoded.status.success());
+    assert_eq!(decoded.stdout, b"repaired on the way");
+}
+
+#[test]
+fn test_inspect() {
+    let mut packet_data = "abcdefgh".to_packet_data(4);
+    packet_data[13] ^= 1;
+
+    let output = run(&["inspect"], &packet_data);
+    assert!(output.status.success());
+    let lines: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1], "0 1 4 additive - no yes");
+    assert!(lines[2].starts_with("10 1 4 additive - no no (Checksum invalid"));
+}
+
+#[test]
+fn test_usage_errors() {
+    let output = run(&["encode", "--bogus"], b"");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("unknown option `--bogus`"));
+
+    let output = run(&["encode", "--packet-size", "0"], b"");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_dump() {
+    let packet_data = "dump me".to_packet_data(16);
+
+    let output = run(&["dump"], &packet_data);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{}", StreamDump::new(&packet_data))
+    );
+}
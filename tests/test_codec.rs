@@ -0,0 +1,203 @@
+use std::io;
+
+use futures::{SinkExt, StreamExt};
+use tokio::io::AsyncWriteExt;
+use tokio_util::codec::{FramedRead, FramedWrite};
+
+use solution::*;
+
+fn packet_error(error: &io::Error) -> &PacketError {
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    error.get_ref().unwrap().downcast_ref().unwrap()
+}
+
+#[tokio::test]
+async fn test_packets_over_duplex() {
+    // A small duplex buffer splits the packets across several reads.
+    let (client, server) = tokio::io::duplex(5);
+    let builder = PacketBuilder::new(4).checksum(ChecksumAlgorithm::Crc32c);
+    let source = b"sent through a duplex stream";
+
+    let writer = tokio::spawn(async move {
+        let mut sink = FramedWrite::new(client, PacketCodec::new());
+        for packet in builder.packets(source) {
+            sink.send(packet).await.unwrap();
+        }
+    });
+
+    let packets: Vec<PacketBuf> = FramedRead::new(server, PacketCodec::new())
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    writer.await.unwrap();
+
+    assert_eq!(packets.len(), 7);
+    let payload: Vec<u8> = packets
+        .iter()
+        .flat_map(|packet| packet.payload().to_vec())
+        .collect();
+    assert_eq!(payload, source);
+    assert_eq!(
+        packets
+            .iter()
+            .flat_map(|packet| packet.as_bytes().to_vec())
+            .collect::<Vec<u8>>(),
+        source.to_packet_data_with(&builder)
+    );
+}
+
+#[tokio::test]
+async fn test_packet_errors_are_located() {
+    let mut packet_data = "abcdefgh".to_packet_data(4);
+    packet_data[12] ^= 1;
+
+    let mut packets = FramedRead::new(&packet_data[..], PacketCodec::new());
+    assert_eq!(packets.next().await.unwrap().unwrap().payload(), b"abcd");
+
+    let error = packets.next().await.unwrap().unwrap_err();
+    let error = packet_error(&error);
+    assert!(matches!(
+        error.kind(),
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+    assert_eq!(error.offset(), Some(10));
+    assert_eq!(error.packet_index(), Some(1));
+}
+
+#[tokio::test]
+async fn test_stream_ending_inside_packet() {
+    let packet_data = "abcdefgh".to_packet_data(4);
+
+    let mut packets = FramedRead::new(&packet_data[..18], PacketCodec::new());
+    assert!(packets.next().await.unwrap().is_ok());
+
+    let error = packets.next().await.unwrap().unwrap_err();
+    assert_eq!(
+        packet_error(&error).kind(),
+        &PacketErrorKind::InvalidPacket {
+            declared: 10,
+            available: 8
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_framed_messages_over_duplex() {
+    let (client, server) = tokio::io::duplex(16);
+    let builder = PacketBuilder::new(3).framed();
+    let messages = vec![
+        String::from("first message"),
+        String::new(),
+        String::from("ünïcödé split mid character"),
+    ];
+
+    let sent = messages.clone();
+    let writer = tokio::spawn(async move {
+        let mut sink = FramedWrite::new(client, MessageCodec::new(builder));
+        for message in &sent {
+            sink.send(message).await.unwrap();
+        }
+    });
+
+    let received: Vec<String> = FramedRead::new(server, MessageCodec::new(builder))
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    writer.await.unwrap();
+
+    assert_eq!(received, messages);
+}
+
+#[tokio::test]
+async fn test_sequenced_messages() {
+    let builder = PacketBuilder::new(2).sequenced(9);
+    let mut sink = FramedWrite::new(Vec::new(), MessageCodec::<Vec<u8>>::new(builder));
+    sink.send(vec![1, 2, 3, 4, 5]).await.unwrap();
+    sink.send(vec![6]).await.unwrap();
+    sink.send(vec![6]).await.unwrap();
+    let packet_data = sink.into_inner();
+
+    let mut messages = FramedRead::new(&packet_data[..], MessageCodec::<Vec<u8>>::new(builder));
+    assert_eq!(messages.next().await.unwrap().unwrap(), vec![1, 2, 3, 4, 5]);
+    // Repeating a message with the same id is fine, as messages are not interleaved.
+    assert_eq!(messages.next().await.unwrap().unwrap(), vec![6]);
+    assert_eq!(messages.next().await.unwrap().unwrap(), vec![6]);
+    assert!(messages.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_stream_ending_inside_message() {
+    let builder = PacketBuilder::new(4).framed();
+    let mut packet_data = "complete".to_packet_data_with(&builder);
+    let (client, server) = tokio::io::duplex(64);
+
+    // Leave out the last packet of the second message.
+    let second = "cut short".to_packet_data_with(&builder);
+    packet_data.extend_from_slice(&second[..(2 * (4 + 8))]);
+    let writer = tokio::spawn(async move {
+        let mut client = client;
+        client.write_all(&packet_data).await.unwrap();
+    });
+
+    let mut messages = FramedRead::new(server, MessageCodec::<String>::new(builder));
+    assert_eq!(messages.next().await.unwrap().unwrap(), "complete");
+
+    let error = messages.next().await.unwrap().unwrap_err();
+    let error = packet_error(&error);
+    assert_eq!(error.kind(), &PacketErrorKind::IncompleteMessage);
+    assert_eq!(error.offset(), Some(4 * (4 + 8)));
+    writer.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_invalid_builder_is_reported_by_encoder() {
+    let builder = PacketBuilder::new(300).version(ProtocolVersion::V1);
+    let mut sink = FramedWrite::new(Vec::new(), MessageCodec::<String>::new(builder));
+
+    let error = sink.send(String::from("too big")).await.unwrap_err();
+
+    assert_eq!(
+        packet_error(&error).kind(),
+        &PacketErrorKind::InvalidPacketSize
+    );
+    assert!(sink.get_ref().is_empty());
+}
+
+#[tokio::test]
+async fn test_unterminated_message_is_limited() {
+    let builder = PacketBuilder::new(4).framed();
+    // Drop the last packet, so that the message never ends.
+    let mut packet_data = "never terminated".to_packet_data_with(&builder);
+    packet_data.truncate(3 * (4 + 8));
+
+    let codec = MessageCodec::<String>::new(builder).max_message_length(2 * (4 + 8));
+    let mut messages = FramedRead::new(&packet_data[..], codec);
+
+    let error = messages.next().await.unwrap().unwrap_err();
+    let error = packet_error(&error);
+    assert_eq!(
+        error.kind(),
+        &PacketErrorKind::MessageTooLarge { limit: 2 * (4 + 8) }
+    );
+    assert_eq!(error.packet_index(), Some(2));
+}
+
+#[test]
+fn test_oversized_sequenced_message_is_dropped() {
+    use tokio_util::bytes::BytesMut;
+    use tokio_util::codec::Decoder;
+
+    let builder = PacketBuilder::new(4).sequenced(2);
+    let mut codec = MessageCodec::<String>::new(builder).max_message_length(4 + 6 + 8);
+
+    let mut src = BytesMut::from(&"far too long".to_packet_data_with(&builder)[..]);
+    let error = codec.decode(&mut src).unwrap_err();
+    assert_eq!(
+        packet_error(&error).kind(),
+        &PacketErrorKind::MessageTooLarge { limit: 4 + 6 + 8 }
+    );
+
+    // The rejected packet did not count towards the next message with the same id.
+    let mut src = BytesMut::from(&"fits".to_packet_data_with(&builder)[..]);
+    assert_eq!(codec.decode(&mut src).unwrap(), Some("fits".to_string()));
+}
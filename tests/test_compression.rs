@@ -0,0 +1,203 @@
+use solution::*;
+
+fn text() -> String {
+    "the quick brown fox jumps over the lazy dog. ".repeat(200)
+}
+
+/// Serializes a single packet flagged as compressed, whatever its payload.
+fn compressed_packet(payload: &[u8]) -> Vec<u8> {
+    let mut packet_data = payload.to_packet_data_with(&PacketBuilder::new(1024).framed());
+    packet_data[1] |= 0b0010_0000;
+    // The checksum covers the flags as well.
+    let end = packet_data.len() - 4;
+    let checksum = Additive.checksum(&packet_data[..end]);
+    packet_data[end..].copy_from_slice(&checksum);
+    packet_data
+}
+
+#[test]
+fn test_round_trip() {
+    for &compression in &[Compression::Deflate, Compression::Lz4] {
+        let builder = PacketBuilder::new(64)
+            .checksum(ChecksumAlgorithm::Crc32)
+            .compression(compression);
+        let packet_data = text().to_packet_data_with(&builder);
+
+        assert!(packet_data.len() < text().len() / 4, "{:?}", compression);
+        assert!(PacketIter::new(&packet_data).all(|packet| packet.unwrap().is_compressed()));
+        assert_eq!(String::from_packet_data(&packet_data).unwrap(), text());
+    }
+}
+
+#[test]
+fn test_round_trip_sequenced_and_framed() {
+    let builder = PacketBuilder::new(16)
+        .sequenced(4)
+        .framed()
+        .compression(Compression::Lz4);
+    let mut packet_data = text().to_packet_data_with(&builder);
+    packet_data.extend(Vec::<u8>::new().to_packet_data_with(&builder));
+
+    let (first, rest) = String::next_message(&packet_data).unwrap();
+    assert_eq!(first, text());
+    assert_eq!(Vec::<u8>::from_packet_data(rest).unwrap(), Vec::<u8>::new());
+}
+
+#[test]
+fn test_packet_count_is_checked_after_compression() {
+    let source = vec![0u8; 200_000];
+    let builder = PacketBuilder::new(2).sequenced(1);
+    assert_eq!(
+        source.try_to_packet_data_with(&builder),
+        Err(PacketErrorKind::MessageTooLong.into())
+    );
+
+    let packet_data = source.to_packet_data_with(&builder.compression(Compression::Deflate));
+    assert_eq!(Vec::<u8>::from_packet_data(&packet_data).unwrap(), source);
+}
+
+#[test]
+fn test_packets_are_not_compressed() {
+    let builder = PacketBuilder::new(1024).compression(Compression::Deflate);
+    let text = text();
+
+    let packet = builder.packets(text.as_bytes()).next().unwrap();
+
+    assert!(!packet.is_compressed());
+    assert_eq!(packet.payload(), &text.as_bytes()[..1024]);
+}
+
+#[test]
+fn test_decompression_limit() {
+    let zeros = vec![0u8; 1 << 20];
+
+    for &compression in &[Compression::Deflate, Compression::Lz4] {
+        let builder = PacketBuilder::new(1024).compression(compression);
+        let packet_data = zeros.to_packet_data_with(&builder);
+
+        let error = Vec::<u8>::from_packet_data_with_limit(&packet_data, 1000).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &PacketErrorKind::MessageTooLarge { limit: 1000 }
+        );
+        assert_eq!(
+            Vec::<u8>::from_packet_data_with_limit(&packet_data, 1 << 20).unwrap(),
+            zeros
+        );
+    }
+}
+
+#[test]
+fn test_invalid_compressed_messages() {
+    let error_kind = |payload: &[u8]| {
+        let error = Vec::<u8>::from_packet_data(&compressed_packet(payload)).unwrap_err();
+        assert_eq!(error.offset(), Some(0));
+        error.kind().clone()
+    };
+
+    assert_eq!(
+        error_kind(&[9, 1, 2, 3]),
+        PacketErrorKind::UnknownCompression(9)
+    );
+    assert_eq!(error_kind(&[]), PacketErrorKind::CorruptedMessage);
+    assert_eq!(
+        error_kind(&[1, 0xff, 0xff, 0xff]),
+        PacketErrorKind::CorruptedMessage
+    );
+    assert_eq!(
+        error_kind(&[2, 4, 0, 0, 0, 0x40]),
+        PacketErrorKind::CorruptedMessage
+    );
+}
+
+#[test]
+fn test_back_to_back_compressed_messages() {
+    for &compression in &[Compression::Deflate, Compression::Lz4] {
+        let builder = PacketBuilder::new(16).framed().compression(compression);
+        let mut packet_data = text().to_packet_data_with(&builder);
+        packet_data.extend("second message".to_packet_data_with(&builder));
+
+        assert_eq!(
+            String::from_packet_data(&packet_data).unwrap(),
+            text() + "second message"
+        );
+        let error = Vec::<u8>::from_packet_data_with_limit(&packet_data, text().len()).unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &PacketErrorKind::MessageTooLarge {
+                limit: text().len()
+            }
+        );
+
+        // A damaged message is reported at its first packet.
+        let offset = packet_data.len();
+        let index = PacketIter::new(&packet_data).count();
+        packet_data.extend(compressed_packet(&[compression.id(), 0xff]));
+        let error = Vec::<u8>::from_packet_data(&packet_data).unwrap_err();
+        assert_eq!(error.kind(), &PacketErrorKind::CorruptedMessage);
+        assert_eq!(error.offset(), Some(offset));
+        assert_eq!(error.packet_index(), Some(index));
+    }
+}
+
+#[test]
+fn test_mixed_compression_is_rejected() {
+    let mut packet_data = "plain".to_packet_data_with(&PacketBuilder::new(8));
+    let offset = packet_data.len();
+    packet_data.extend(compressed_packet(&[1]));
+
+    let error = Vec::<u8>::from_packet_data(&packet_data).unwrap_err();
+
+    assert_eq!(error.kind(), &PacketErrorKind::InvalidFlags(0b0011_0000));
+    assert_eq!(error.offset(), Some(offset));
+    assert_eq!(error.packet_index(), Some(1));
+}
+
+#[test]
+fn test_compression_needs_version_2() {
+    let builder = PacketBuilder::new(8)
+        .version(ProtocolVersion::V1)
+        .compression(Compression::Deflate);
+
+    assert_eq!(
+        builder.validate(),
+        Err(PacketErrorKind::UnsupportedByVersion.into())
+    );
+    assert_eq!(
+        "text".try_to_packet_data_with(&builder),
+        Err(PacketErrorKind::UnsupportedByVersion.into())
+    );
+}
+
+#[test]
+fn test_compression_ids() {
+    for &compression in &[Compression::Deflate, Compression::Lz4] {
+        assert!(compression.is_enabled());
+        assert_eq!(Compression::from_id(compression.id()), Ok(compression));
+    }
+    assert_eq!(
+        Compression::from_id(0),
+        Err(PacketErrorKind::UnknownCompression(0).into())
+    );
+}
+
+#[test]
+fn test_reassembler_rejects_compressed_packets() {
+    let builder = PacketBuilder::new(16)
+        .sequenced(2)
+        .compression(Compression::Deflate);
+    let packet_data = text().to_packet_data_with(&builder);
+    let mut reassembler = Reassembler::new();
+
+    for packet in PacketIter::new(&packet_data) {
+        assert_eq!(
+            reassembler.push(&packet.unwrap()),
+            Err(PacketErrorKind::CompressedPacket.into())
+        );
+    }
+    assert_eq!(reassembler.pending(), 0);
+
+    let (message, rest) = String::next_message(&packet_data).unwrap();
+    assert_eq!(message, text());
+    assert!(rest.is_empty());
+}
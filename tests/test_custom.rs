@@ -1,3 +1,5 @@
+#![allow(clippy::manual_ok_err)]
+
 use solution::*;
 
 #[test]
@@ -35,12 +37,24 @@ fn test_incoming_zero() {
 
 #[test]
 fn test_tampered_data() {
-    tamper_data(3, PacketError::InvalidChecksum);
-    tamper_data(0, PacketError::UnknownProtocolVersion);
-    tamper_data(1, PacketError::InvalidPacket);
+    assert!(matches!(
+        tamper_data(3).kind(),
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+    assert_eq!(
+        tamper_data(0).kind(),
+        &PacketErrorKind::UnknownProtocolVersion(100)
+    );
+    assert_eq!(
+        tamper_data(1).kind(),
+        &PacketErrorKind::InvalidPacket {
+            declared: 106,
+            available: 37
+        }
+    );
 }
 
-fn tamper_data(index: usize, expected_error: PacketError) {
+fn tamper_data(index: usize) -> PacketError {
     let initial_data = String::from("messageсда");
     let mut packet_data = initial_data.to_packet_data(4);
 
@@ -51,7 +65,7 @@ fn tamper_data(index: usize, expected_error: PacketError) {
         Err(error) => Some(error),
     };
 
-    assert_eq!(result.unwrap(), expected_error);
+    result.unwrap()
 }
 
 #[test]
@@ -62,3 +76,184 @@ fn test_downscaled_size() {
 
     assert_eq!(serialized[1] as usize, initial_data.len());
 }
+
+#[test]
+fn test_v2_large_packets() {
+    let initial_data = "x".repeat(70_000);
+    let builder = PacketBuilder::new(u16::MAX);
+    let packet_data = initial_data.to_packet_data_with(&builder);
+
+    assert_eq!(packet_data[0], 2);
+    assert_eq!(&packet_data[2..4], &u16::MAX.to_be_bytes());
+    assert_eq!(packet_data.len(), initial_data.len() + 2 * 8);
+
+    let restored_data = String::from_packet_data(&packet_data).unwrap();
+    assert_eq!(initial_data, restored_data);
+}
+
+#[test]
+fn test_mixed_versions() {
+    let mut packet_data = String::from("old").to_packet_data(2);
+    packet_data.extend(String::from("new").to_packet_data_with(&PacketBuilder::new(2)));
+
+    assert_eq!(String::from_packet_data(&packet_data).unwrap(), "oldnew");
+}
+
+#[test]
+fn test_v2_header_is_checksummed() {
+    let builder = PacketBuilder::new(4).framed();
+    let mut packet_data = "abcdefgh".to_packet_data_with(&builder);
+    packet_data[1] |= 0b0001_0000;
+
+    let error = String::next_message(&packet_data).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+    assert_eq!(error.offset(), Some(0));
+}
+
+#[test]
+fn test_v2_parity_flag() {
+    // The last bit of the flags byte marks parity packets, which do not carry the message.
+    let mut packet_data = String::from("flags").to_packet_data_with(&PacketBuilder::new(300));
+    packet_data[1] = 0b1000_0000;
+    assert!(matches!(
+        Packet::deserialize(&packet_data).unwrap_err().kind(),
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+
+    // The checksum covers the header, so it has to be recomputed.
+    let end = packet_data.len() - 4;
+    let checksum = Additive.checksum(&packet_data[..end]);
+    packet_data[end..].copy_from_slice(&checksum);
+    let (packet, _) = Packet::deserialize(&packet_data).unwrap();
+    assert!(packet.is_parity());
+    let error = String::from_packet_data(&packet_data).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::IncompleteMessage);
+}
+
+#[test]
+fn test_invalid_packet_sizes() {
+    assert_eq!(
+        Packet::try_from_source(b"data", 0),
+        Err(PacketErrorKind::InvalidPacketSize.into())
+    );
+    assert_eq!(
+        String::from("data").try_to_packet_data(0),
+        Err(PacketErrorKind::InvalidPacketSize.into())
+    );
+    assert!("data".try_to_packets(0).is_err());
+    assert_eq!(
+        PacketBuilder::new(256)
+            .version(ProtocolVersion::V1)
+            .validate(),
+        Err(PacketErrorKind::InvalidPacketSize.into())
+    );
+    assert_eq!(
+        PacketBuilder::new(16)
+            .version(ProtocolVersion::V1)
+            .framed()
+            .try_build(b"data")
+            .err(),
+        Some(PacketErrorKind::UnsupportedByVersion.into())
+    );
+
+    let (packet, remainder) = Packet::try_from_source(b"data", 3).unwrap();
+    assert_eq!(packet.payload(), b"dat");
+    assert_eq!(remainder, b"a");
+}
+
+#[test]
+fn test_too_many_sequenced_packets() {
+    let source = vec![0u8; u16::MAX as usize + 1];
+    let builder = PacketBuilder::new(1).sequenced(1);
+
+    assert_eq!(
+        source.try_to_packet_data_with(&builder),
+        Err(PacketErrorKind::MessageTooLong.into())
+    );
+    assert!(source
+        .try_to_packet_data_with(&builder.packet_size(2))
+        .is_ok());
+}
+
+#[test]
+#[should_panic]
+fn test_zero_packet_size_panics() {
+    Packet::from_source(b"data", 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_writer_reports_invalid_size() {
+    use std::io::Write;
+
+    let mut writer = PacketWriter::new(Vec::new(), PacketBuilder::new(0));
+    let error = writer.write_all(b"data").unwrap_err();
+
+    let packet_error = error.get_ref().unwrap().downcast_ref::<PacketError>();
+    assert_eq!(
+        packet_error.map(PacketError::kind),
+        Some(&PacketErrorKind::InvalidPacketSize)
+    );
+}
+
+#[test]
+fn test_error_location_and_details() {
+    let mut packet_data = String::from("abcdefgh").to_packet_data(4);
+    packet_data[13] = b'x';
+
+    let error = String::from_packet_data(&packet_data).unwrap_err();
+    assert_eq!(
+        error.kind(),
+        &PacketErrorKind::InvalidChecksum {
+            expected: Additive.checksum(b"efgh"),
+            computed: Additive.checksum(b"exgh"),
+        }
+    );
+    assert_eq!(error.offset(), Some(10));
+    assert_eq!(error.packet_index(), Some(1));
+    assert_eq!(
+        error.to_string(),
+        "Checksum invalid: expected 0000019a, computed 000001ac in packet 1 at byte 10"
+    );
+
+    let error = Packet::deserialize(&packet_data[10..]).unwrap_err();
+    assert_eq!(error.offset(), None);
+    assert_eq!(
+        error.to_string(),
+        "Checksum invalid: expected 0000019a, computed 000001ac"
+    );
+}
+
+#[test]
+fn test_invalid_utf8_source() {
+    let packet_data = b"ok\xFFok".to_packet_data(4);
+    let error = String::from_packet_data(&packet_data).unwrap_err();
+
+    assert_eq!(error.kind(), &PacketErrorKind::CorruptedMessage);
+    let utf8_error = error.utf8_error().unwrap();
+    assert_eq!(utf8_error.utf8_error().valid_up_to(), 2);
+    assert_eq!(utf8_error.as_bytes(), b"ok\xFFok");
+    #[cfg(feature = "std")]
+    {
+        use std::error::Error;
+        assert!(error.source().unwrap().is::<std::string::FromUtf8Error>());
+    }
+}
+
+#[test]
+fn test_lossy_decode() {
+    let packet_data = b"caf\xE9 \xF0\x9F\x98 ok \xFF".to_packet_data(4);
+
+    let (message, invalid_ranges) = from_packet_data_lossy(&packet_data).unwrap();
+    assert_eq!(message, "caf\u{FFFD} \u{FFFD} ok \u{FFFD}");
+    assert_eq!(invalid_ranges, vec![3..4, 5..8, 12..13]);
+
+    let packet_data = "valid".to_packet_data(4);
+    assert_eq!(
+        from_packet_data_lossy(&packet_data).unwrap(),
+        (String::from("valid"), Vec::new())
+    );
+}
@@ -0,0 +1,89 @@
+use solution::*;
+
+#[test]
+fn test_decode_incomplete() {
+    let packet_data = "partial".to_packet_data_with(&PacketBuilder::new(300));
+
+    assert_eq!(Packet::decode(&[]), Ok(Decoded::Incomplete(6)));
+    assert_eq!(
+        Packet::decode(&packet_data[..1]),
+        Ok(Decoded::Incomplete(7))
+    );
+    assert_eq!(
+        Packet::decode(&packet_data[..4]),
+        Ok(Decoded::Incomplete(packet_data.len() - 4))
+    );
+    assert_eq!(
+        Packet::decode(&[9, 1, 2]),
+        Err(PacketErrorKind::UnknownProtocolVersion(9).into())
+    );
+
+    match Packet::decode(&packet_data) {
+        Ok(Decoded::Packet(packet, remainder)) => {
+            assert_eq!(packet.payload(), b"partial");
+            assert!(remainder.is_empty());
+        }
+        other => panic!("Unexpected result {:?}", other),
+    }
+}
+
+#[test]
+fn test_decoder_across_chunks() {
+    let packet_data = "chunked".to_packet_data(4);
+    let mut decoder = PacketDecoder::new();
+
+    assert_eq!(decoder.feed(&packet_data[..5]).count(), 0);
+    assert_eq!(decoder.needed(), 5);
+
+    let packets: Vec<PacketBuf> = decoder
+        .feed(&packet_data[5..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].payload(), b"chun");
+    assert_eq!(packets[1].payload(), b"ked");
+    assert!(decoder.buffered().is_empty());
+}
+
+#[test]
+fn test_decoder_byte_by_byte() {
+    let message = "one byte at a time";
+    let packet_data = message.to_packet_data_with(&PacketBuilder::new(5));
+    let mut decoder = PacketDecoder::new();
+    let mut restored_data = Vec::new();
+
+    for byte in packet_data.iter() {
+        for packet in decoder.feed(&[*byte]) {
+            let packet = packet.unwrap();
+            assert_eq!(packet.as_bytes(), &packet.packet().serialize()[..]);
+            restored_data.extend_from_slice(packet.payload());
+        }
+    }
+
+    assert_eq!(restored_data, message.as_bytes());
+    assert_eq!(decoder.needed(), 6);
+}
+
+#[test]
+fn test_decoder_malformed_input() {
+    let mut packet_data = "good".to_packet_data(4);
+    let mut bad_data = "bad".to_packet_data(4);
+    bad_data[2] ^= 1;
+    packet_data.extend(bad_data);
+
+    let mut decoder = PacketDecoder::new();
+    let results: Vec<_> = decoder.feed(&packet_data).collect();
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap().payload(), b"good");
+    let error = results[1].as_ref().unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+    assert_eq!((error.offset(), error.packet_index()), (Some(10), Some(1)));
+    assert_eq!(decoder.needed(), 0);
+
+    decoder.clear();
+    assert_eq!(decoder.feed(&"next".to_packet_data(4)).count(), 1);
+}
@@ -0,0 +1,138 @@
+use solution::*;
+use solution_derive::Packetable;
+
+#[derive(Packetable, Debug, PartialEq)]
+struct Reading {
+    sensor: String,
+    value: i32,
+    calibrated: bool,
+    history: Vec<u16>,
+    note: Option<String>,
+}
+
+#[derive(Packetable, Debug, PartialEq)]
+struct Position(f64, f64);
+
+#[derive(Packetable, Debug, PartialEq)]
+struct Heartbeat;
+
+#[derive(Packetable, Debug, PartialEq)]
+enum Command {
+    Stop,
+    Move(Position),
+    Report { reading: Reading, urgent: bool },
+}
+
+#[derive(Packetable, Debug, PartialEq)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+fn reading() -> Reading {
+    Reading {
+        sensor: String::from("термометър"),
+        value: -40,
+        calibrated: true,
+        history: vec![1, 2, 300],
+        note: None,
+    }
+}
+
+#[test]
+fn test_struct_round_trip() {
+    let initial_data = reading();
+    let packet_data = initial_data.to_packet_data(4);
+
+    assert_eq!(
+        Reading::from_packet_data(&packet_data).unwrap(),
+        initial_data
+    );
+    assert_eq!(
+        Position::from_packet_data(&Position(1.5, -2.0).to_packet_data(3)).unwrap(),
+        Position(1.5, -2.0)
+    );
+    assert_eq!(
+        Heartbeat::from_packet_data(&Heartbeat.to_packet_data(3)).unwrap(),
+        Heartbeat
+    );
+}
+
+#[test]
+fn test_enum_round_trip() {
+    let commands = vec![
+        Command::Stop,
+        Command::Move(Position(0.25, 8.0)),
+        Command::Report {
+            reading: reading(),
+            urgent: false,
+        },
+    ];
+
+    for command in commands {
+        let packet_data = command.to_packet_data_with(&PacketBuilder::new(16));
+        assert_eq!(Command::from_packet_data(&packet_data).unwrap(), command);
+    }
+}
+
+#[test]
+fn test_generic_round_trip() {
+    let initial_data = Wrapper { inner: 42u64 };
+    let packet_data = initial_data.to_packet_data(5);
+
+    assert_eq!(
+        Wrapper::<u64>::from_packet_data(&packet_data).unwrap(),
+        initial_data
+    );
+}
+
+#[test]
+fn test_deterministic_layout() {
+    let payload = Vec::<u8>::from_packet_data(&Position(1.0, 2.0).to_packet_data(255)).unwrap();
+
+    let mut expected = 1.0f64.to_be_bytes().to_vec();
+    expected.extend_from_slice(&2.0f64.to_be_bytes());
+    assert_eq!(payload, expected);
+
+    let payload = Vec::<u8>::from_packet_data(&Command::Stop.to_packet_data(255)).unwrap();
+    assert_eq!(payload, vec![0, 0, 0, 0]);
+}
+
+#[test]
+fn test_field_errors() {
+    let mut payload = Vec::new();
+    1u32.encode_field(&mut payload).unwrap();
+    payload.push(0);
+
+    let packet_data = payload.to_packet_data(255);
+    assert_eq!(
+        Command::from_packet_data(&packet_data),
+        Err(PacketErrorKind::InvalidField("Position.0").into())
+    );
+
+    let mut payload = Vec::new();
+    String::from("sensor").encode_field(&mut payload).unwrap();
+    7i32.encode_field(&mut payload).unwrap();
+    payload.push(2);
+
+    let packet_data = payload.to_packet_data(255);
+    assert_eq!(
+        Reading::from_packet_data(&packet_data),
+        Err(PacketErrorKind::InvalidField("Reading.calibrated").into())
+    );
+
+    let packet_data = vec![0, 0, 0, 9].to_packet_data(255);
+    assert_eq!(
+        Command::from_packet_data(&packet_data),
+        Err(PacketErrorKind::InvalidField("Command").into())
+    );
+}
+
+#[test]
+fn test_trailing_bytes() {
+    let packet_data = vec![0, 0, 0, 0, 1].to_packet_data(255);
+
+    assert_eq!(
+        Command::from_packet_data(&packet_data),
+        Err(PacketErrorKind::CorruptedMessage.into())
+    );
+}
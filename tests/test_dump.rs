@@ -0,0 +1,59 @@
+use solution::*;
+
+#[test]
+fn test_hex_dump() {
+    let bytes: Vec<u8> = (0x5e..0x72).collect();
+
+    assert_eq!(
+        HexDump(&bytes).to_string(),
+        "  0000  5e 5f 60 61 62 63 64 65  66 67 68 69 6a 6b 6c 6d  |^_`abcdefghijklm|\n\
+         \x20 0010  6e 6f 70 71                                       |nopq|\n"
+    );
+    assert_eq!(
+        HexDump(b"\n\x7f ").to_string().split('|').nth(1),
+        Some(".. ")
+    );
+}
+
+#[test]
+fn test_packet_display() {
+    let builder = PacketBuilder::new(8)
+        .checksum(ChecksumAlgorithm::Crc32)
+        .sequenced(7)
+        .framed();
+    let (packet, _) = builder.build(b"hi");
+
+    let serialized = packet.serialize();
+    let checksum = u32::from_be_bytes(Crc32.checksum(&serialized[..(serialized.len() - 4)]));
+    assert_eq!(
+        packet.to_string(),
+        format!(
+            "Packet v2, 2 bytes, Crc32, sequence 7:0/1, last\n\
+             \x20 0000  68 69                                             |hi|\n\
+             \x20 checksum {:08x} ✓",
+            checksum
+        )
+    );
+}
+
+#[test]
+fn test_stream_dump_marks_damage() {
+    let mut packet_data = "abcdefghijkl".to_packet_data(4);
+    packet_data[13] ^= 1;
+    packet_data.insert(20, 0xFF);
+
+    let dump = StreamDump::new(&packet_data).to_string();
+    let headers: Vec<&str> = dump.lines().filter(|line| !line.starts_with(' ')).collect();
+
+    assert_eq!(
+        headers,
+        vec![
+            "#0 at byte 0: Packet v1, 4 bytes",
+            "#1 at byte 10: Packet v1, 4 bytes",
+            "bytes 20..21 skipped: Unknown protocol version 255",
+            "#2 at byte 21: Packet v1, 4 bytes",
+        ]
+    );
+    assert!(dump.contains("checksum 0000019a ✗ (computed 0000019b)"));
+    assert!(dump.contains("  0000  ff "));
+}
@@ -0,0 +1,206 @@
+use solution::*;
+
+const KEY: [u8; 32] = [7; 32];
+const ALGORITHMS: [CipherAlgorithm; 2] = [
+    CipherAlgorithm::ChaCha20Poly1305,
+    CipherAlgorithm::Aes256Gcm,
+];
+
+fn builder() -> PacketBuilder {
+    PacketBuilder::new(8)
+        .checksum(ChecksumAlgorithm::Crc32)
+        .sequenced(3)
+        .framed()
+}
+
+/// Start offsets of the packets in `packet_data`.
+fn packet_offsets(packet_data: &[u8]) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+    for packet in PacketIter::new(packet_data) {
+        offsets.push(offset);
+        offset += packet.unwrap().encoded_len();
+    }
+    offsets
+}
+
+#[test]
+fn test_round_trip() {
+    let message = "sent over an untrusted link";
+
+    for &algorithm in &ALGORITHMS {
+        let packet_data = message.to_packet_data_with(&builder());
+        let sealed = PacketCipher::new(algorithm, &KEY)
+            .seal(&packet_data)
+            .unwrap();
+
+        let opened = PacketCipher::new(algorithm, &KEY).open(&sealed).unwrap();
+        assert_eq!(opened, packet_data);
+        assert_eq!(String::from_packet_data(&opened).unwrap(), message);
+    }
+}
+
+#[test]
+fn test_sealed_packets() {
+    let packet_data = "secret payload".to_packet_data_with(&builder());
+    let mut cipher = PacketCipher::new(CipherAlgorithm::ChaCha20Poly1305, &KEY);
+    let sealed = cipher.seal(&packet_data).unwrap();
+
+    assert_eq!(cipher.counter(), 2);
+    for (plain, sealed) in PacketIter::new(&packet_data).zip(PacketIter::new(&sealed)) {
+        let (plain, sealed) = (plain.unwrap(), sealed.unwrap());
+        assert!(sealed.is_encrypted());
+        assert_eq!(sealed.sequence(), plain.sequence());
+        assert_eq!(
+            sealed.payload().len(),
+            plain.payload().len() + SEAL_OVERHEAD
+        );
+        assert!(!sealed
+            .payload()
+            .windows(plain.payload().len())
+            .any(|window| window == plain.payload()));
+    }
+
+    let error = String::from_packet_data(&sealed).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::EncryptedPacket);
+    assert_eq!(error.offset(), Some(0));
+}
+
+#[test]
+fn test_counter_makes_every_packet_unique() {
+    let packet_data = "same".to_packet_data_with(&builder());
+    let mut cipher = PacketCipher::new(CipherAlgorithm::Aes256Gcm, &KEY).with_counter(41);
+
+    let first = cipher.seal(&packet_data).unwrap();
+    let second = cipher.seal(&packet_data).unwrap();
+
+    assert_ne!(first, second);
+    assert_eq!(cipher.counter(), 43);
+    // The counter follows the 10 byte sequenced header.
+    assert_eq!(&first[10..18], &41u64.to_be_bytes());
+    assert_eq!(cipher.open(&second).unwrap(), packet_data);
+}
+
+#[test]
+fn test_exhausted_counter_fails() {
+    let packet_data = "two packets".to_packet_data_with(&builder());
+    let mut cipher =
+        PacketCipher::new(CipherAlgorithm::ChaCha20Poly1305, &KEY).with_counter(u64::MAX - 1);
+
+    let error = cipher.seal(&packet_data).unwrap_err();
+
+    assert_eq!(error.kind(), &PacketErrorKind::CounterExhausted);
+    assert_eq!(error.packet_index(), Some(1));
+    assert_eq!(cipher.counter(), u64::MAX);
+}
+
+#[test]
+fn test_reordered_packets_open() {
+    let message = "reordered on the way";
+    let cipher = PacketCipher::new(CipherAlgorithm::ChaCha20Poly1305, &KEY);
+    let sealed = PacketCipher::new(CipherAlgorithm::ChaCha20Poly1305, &KEY)
+        .seal(&message.to_packet_data_with(&builder()))
+        .unwrap();
+
+    let offsets = packet_offsets(&sealed);
+    let mut reordered = sealed[offsets[2]..].to_vec();
+    reordered.extend_from_slice(&sealed[..offsets[2]]);
+
+    let opened = cipher.open(&reordered).unwrap();
+    assert_eq!(String::from_packet_data(&opened).unwrap(), message);
+}
+
+#[test]
+fn test_wrong_key_fails() {
+    let sealed = PacketCipher::new(CipherAlgorithm::Aes256Gcm, &KEY)
+        .seal(&"message".to_packet_data_with(&builder()))
+        .unwrap();
+
+    let error = PacketCipher::new(CipherAlgorithm::Aes256Gcm, &[8; 32])
+        .open(&sealed)
+        .unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+    assert_eq!(error.offset(), Some(0));
+
+    let error = PacketCipher::new(CipherAlgorithm::ChaCha20Poly1305, &KEY)
+        .open(&sealed)
+        .unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+}
+
+#[test]
+fn test_tampering_fails() {
+    let cipher = PacketCipher::new(CipherAlgorithm::ChaCha20Poly1305, &KEY);
+    let sealed = PacketCipher::new(CipherAlgorithm::ChaCha20Poly1305, &KEY)
+        .seal(&"two packets long".to_packet_data_with(&builder()))
+        .unwrap();
+    let second = packet_offsets(&sealed)[1];
+
+    // A changed flag with a checksum to match is still caught by the tag.
+    let mut dropped_last = sealed.clone();
+    dropped_last[second + 1] &= !0b0001_0000;
+    let checksum = ChecksumAlgorithm::Crc32.checksum(&dropped_last[second..(sealed.len() - 4)]);
+    let end = dropped_last.len();
+    dropped_last[(end - 4)..].copy_from_slice(&checksum);
+    let error = cipher.open(&dropped_last).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+    assert_eq!(error.offset(), Some(second));
+    assert_eq!(error.packet_index(), Some(1));
+
+    // A changed ciphertext with a checksum to match.
+    let mut forged = sealed.clone();
+    let header_length = 4 + 6;
+    let payload_length = 8 + SEAL_OVERHEAD;
+    let payload = header_length..(header_length + payload_length);
+    forged[payload.start + 12] ^= 1;
+    let checksum = ChecksumAlgorithm::Crc32.checksum(&forged[..payload.end]);
+    forged[payload.end..(payload.end + 4)].copy_from_slice(&checksum);
+    let error = cipher.open(&forged).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+    assert_eq!(error.offset(), Some(0));
+}
+
+#[test]
+fn test_unsealed_packets_fail() {
+    let packet_data = "plain".to_packet_data_with(&builder());
+
+    let error = PacketCipher::new(CipherAlgorithm::Aes256Gcm, &KEY)
+        .open(&packet_data)
+        .unwrap_err();
+
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+}
+
+#[test]
+fn test_seal_needs_version_2() {
+    let mut cipher = PacketCipher::new(CipherAlgorithm::Aes256Gcm, &KEY);
+
+    let error = cipher.seal(&"v1".to_packet_data(4)).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::UnsupportedByVersion);
+    assert_eq!(cipher.counter(), 0);
+
+    let full = vec![0; 65535].to_packet_data_with(&PacketBuilder::new(65535));
+    let error = cipher.seal(&full).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::InvalidPacketSize);
+}
+
+#[test]
+fn test_reader_rejects_sealed_packets() {
+    use std::io::Read;
+
+    let sealed = PacketCipher::new(CipherAlgorithm::Aes256Gcm, &KEY)
+        .seal(&"sealed".to_packet_data_with(&builder()))
+        .unwrap();
+
+    let mut restored_data = Vec::new();
+    let error = PacketReader::new(&sealed[..])
+        .read_to_end(&mut restored_data)
+        .unwrap_err();
+
+    assert!(restored_data.is_empty());
+    let packet_error = error.get_ref().unwrap().downcast_ref::<PacketError>();
+    assert_eq!(
+        packet_error.unwrap().kind(),
+        &PacketErrorKind::EncryptedPacket
+    );
+}
@@ -0,0 +1,93 @@
+use std::io::Write;
+
+use solution::*;
+
+#[test]
+fn test_last_packet_flag() {
+    let builder = PacketBuilder::new(4).framed();
+    let packets: Vec<Packet> = "framed data".to_packets_with(&builder).collect();
+
+    let flags: Vec<bool> = packets.iter().map(Packet::is_last).collect();
+    assert_eq!(flags, vec![false, false, true]);
+
+    let empty: Vec<Packet> = "".to_packets_with(&builder).collect();
+    assert_eq!(empty.len(), 1);
+    assert!(empty[0].is_last());
+}
+
+#[test]
+fn test_back_to_back_messages() {
+    let builder = PacketBuilder::new(3).framed();
+    let mut packet_data = String::from("first").to_packet_data_with(&builder);
+    packet_data.extend(String::new().to_packet_data_with(&builder));
+    packet_data.extend(vec![1u8, 2, 3, 4].to_packet_data_with(&builder));
+
+    let (first, rest) = String::next_message(&packet_data).unwrap();
+    let (empty, rest) = String::next_message(rest).unwrap();
+    let (bytes, rest) = Vec::<u8>::next_message(rest).unwrap();
+
+    assert_eq!(first, "first");
+    assert_eq!(empty, "");
+    assert_eq!(bytes, vec![1, 2, 3, 4]);
+    assert!(rest.is_empty());
+    assert_eq!(
+        String::next_message(rest).unwrap_err().kind(),
+        &PacketErrorKind::IncompleteMessage
+    );
+}
+
+#[test]
+fn test_sequenced_messages_end_on_their_own() {
+    let mut packet_data = "one".to_packet_data_with(&PacketBuilder::new(2).sequenced(1));
+    packet_data.extend("two".to_packet_data_with(&PacketBuilder::new(2).sequenced(2)));
+
+    let (first, rest) = String::next_message(&packet_data).unwrap();
+    let (second, rest) = String::next_message(rest).unwrap();
+
+    assert_eq!((first.as_str(), second.as_str()), ("one", "two"));
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn test_unterminated_message() {
+    let packet_data = "unframed".to_packet_data_with(&PacketBuilder::new(3));
+
+    let error = String::next_message(&packet_data).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::IncompleteMessage);
+    assert_eq!(error.offset(), Some(packet_data.len()));
+    assert_eq!(error.packet_index(), Some(3));
+    assert_eq!(String::from_packet_data(&packet_data).unwrap(), "unframed");
+}
+
+#[test]
+fn test_streaming_messages() {
+    let mut writer = PacketWriter::new(Vec::new(), PacketBuilder::new(4).framed());
+    writer.write_all(b"hello ").unwrap();
+    writer.write_all(b"world").unwrap();
+    writer.end_message().unwrap();
+    writer.write_all(b"abcd").unwrap();
+    writer.end_message().unwrap();
+    writer.end_message().unwrap();
+    writer.write_all(b"cut").unwrap();
+    let packet_data = writer.into_inner().unwrap();
+
+    let mut reader = PacketReader::new(&packet_data[..]);
+    assert_eq!(
+        reader.read_message().unwrap(),
+        Some(b"hello world".to_vec())
+    );
+    assert_eq!(reader.read_message().unwrap(), Some(b"abcd".to_vec()));
+    assert_eq!(reader.read_message().unwrap(), Some(Vec::new()));
+
+    let error = reader.read_message().unwrap_err();
+    let packet_error = error.get_ref().unwrap().downcast_ref::<PacketError>();
+    assert_eq!(
+        packet_error.map(PacketError::kind),
+        Some(&PacketErrorKind::IncompleteMessage)
+    );
+
+    let packet_data = "whole".to_packet_data_with(&PacketBuilder::new(8).framed());
+    let mut reader = PacketReader::new(&packet_data[..]);
+    assert_eq!(reader.read_message().unwrap(), Some(b"whole".to_vec()));
+    assert_eq!(reader.read_message().unwrap(), None);
+}
@@ -0,0 +1,291 @@
+use std::io::{Read, Write};
+
+use solution::*;
+
+#[test]
+fn test_writer_matches_packet_data() {
+    let message = "streamed through a writer in uneven chunks";
+    let builder = PacketBuilder::new(8).checksum(ChecksumAlgorithm::Crc32);
+
+    let mut writer = PacketWriter::new(Vec::new(), builder);
+    for chunk in message.as_bytes().chunks(3) {
+        writer.write_all(chunk).unwrap();
+    }
+    let packet_data = writer.into_inner().unwrap();
+
+    assert_eq!(packet_data, message.to_packet_data_with(&builder));
+    assert_eq!(String::from_packet_data(&packet_data).unwrap(), message);
+}
+
+#[test]
+fn test_writer_emits_full_packets_eagerly() {
+    let mut writer = PacketWriter::new(Vec::new(), PacketBuilder::new(4));
+
+    writer.write_all(b"abc").unwrap();
+    assert!(writer.get_ref().is_empty());
+
+    writer.write_all(b"defghij").unwrap();
+    assert_eq!(writer.get_ref().len(), 2 * (4 + 8));
+
+    writer.flush().unwrap();
+    assert_eq!(
+        String::from_packet_data(writer.get_ref()).unwrap(),
+        "abcdefghij"
+    );
+}
+
+#[test]
+fn test_writer_flushes_on_drop() {
+    let mut packet_data = Vec::new();
+    {
+        let mut writer = PacketWriter::new(&mut packet_data, PacketBuilder::new(100));
+        writer.write_all(b"short").unwrap();
+    }
+
+    assert_eq!(String::from_packet_data(&packet_data).unwrap(), "short");
+}
+
+#[test]
+fn test_writer_rejects_whole_message_builders() {
+    let builder = PacketBuilder::new(4);
+    for &builder in &[
+        builder.sequenced(7),
+        builder.compression(Compression::Lz4),
+        builder.parity(2),
+    ] {
+        let mut writer = PacketWriter::new(Vec::new(), builder);
+
+        let error = writer.write_all(b"abcdefghij").unwrap_err();
+        let packet_error = error.get_ref().unwrap().downcast_ref::<PacketError>();
+        assert_eq!(
+            packet_error.unwrap().kind(),
+            &PacketErrorKind::UnsupportedByWriter
+        );
+        assert!(writer.get_ref().is_empty());
+    }
+}
+
+/// Fails the given number of writes with `WouldBlock`, like a full non-blocking socket.
+struct Blocking {
+    data: Vec<u8>,
+    blocked: usize,
+}
+
+impl std::io::Write for Blocking {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.blocked > 0 {
+            self.blocked -= 1;
+            return Err(std::io::ErrorKind::WouldBlock.into());
+        }
+        self.data.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_writer_keeps_buffer_when_flush_fails() {
+    let inner = Blocking {
+        data: Vec::new(),
+        blocked: 0,
+    };
+    let mut writer = PacketWriter::new(inner, PacketBuilder::new(100));
+    writer.write_all(b"pending").unwrap();
+
+    writer.get_mut().blocked = 1;
+    let error = writer.flush().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::WouldBlock);
+    writer.flush().unwrap();
+
+    assert_eq!(
+        String::from_packet_data(&writer.get_ref().data).unwrap(),
+        "pending"
+    );
+}
+
+#[test]
+fn test_writer_reports_partial_writes() {
+    let inner = Blocking {
+        data: Vec::new(),
+        blocked: 0,
+    };
+    let mut writer = PacketWriter::new(inner, PacketBuilder::new(4));
+    writer.write_all(b"ab").unwrap();
+
+    // The buffered bytes were completed before the packet blocked, so they count as written.
+    writer.get_mut().blocked = 1;
+    assert_eq!(writer.write(b"cdefghij").unwrap(), 2);
+    assert_eq!(writer.write(b"efghij").unwrap(), 6);
+
+    writer.get_mut().blocked = 1;
+    assert_eq!(writer.write(b"klmnopqrst").unwrap(), 2);
+    writer.write_all(b"mnopqrst").unwrap();
+
+    // Nothing was accepted, so the error is returned.
+    writer.get_mut().blocked = 1;
+    let error = writer.write(b"uvwx").unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::WouldBlock);
+    writer.write_all(b"uvwxyz").unwrap();
+    writer.flush().unwrap();
+
+    assert_eq!(
+        String::from_packet_data(&writer.get_ref().data).unwrap(),
+        "abcdefghijklmnopqrstuvwxyz"
+    );
+}
+
+/// Hands out the inner bytes a few at a time, like a slow socket.
+struct Trickle<'a> {
+    data: &'a [u8],
+    step: usize,
+}
+
+impl<'a> std::io::Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.step.min(buf.len()).min(self.data.len());
+        buf[..count].copy_from_slice(&self.data[..count]);
+        self.data = &self.data[count..];
+        Ok(count)
+    }
+}
+
+#[test]
+fn test_reader_round_trip() {
+    let message = "read back through a trickling reader";
+    let mut packet_data = message.to_packet_data(5);
+    packet_data.extend(" and v2".to_packet_data_with(&PacketBuilder::new(300)));
+
+    let mut reader = PacketReader::new(Trickle {
+        data: &packet_data,
+        step: 3,
+    });
+    let mut restored_data = String::new();
+    reader.read_to_string(&mut restored_data).unwrap();
+
+    assert_eq!(restored_data, "read back through a trickling reader and v2");
+}
+
+/// Counts the calls to `read`, like syscalls on an unbuffered socket.
+struct CountingReader<'a> {
+    data: &'a [u8],
+    reads: usize,
+}
+
+impl<'a> std::io::Read for CountingReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reads += 1;
+        self.data.read(buf)
+    }
+}
+
+#[test]
+fn test_reader_reads_headers_at_once() {
+    let packet_data = "abcdefgh".to_packet_data_with(&PacketBuilder::new(4));
+
+    let mut reader = PacketReader::new(CountingReader {
+        data: &packet_data,
+        reads: 0,
+    });
+    let mut restored_data = Vec::new();
+    reader.read_to_end(&mut restored_data).unwrap();
+
+    assert_eq!(restored_data, b"abcdefgh");
+    // The header, then the payload and checksum of both packets, plus the read that finds
+    // the end of the stream.
+    assert_eq!(reader.get_ref().reads, 2 * 2 + 1);
+}
+
+#[test]
+fn test_reader_rejects_sequenced_packets() {
+    let packet_data = "abcdef".to_packet_data_with(&PacketBuilder::new(2).sequenced(1));
+    let (first, rest) = packet_data.split_at(packet_data.len() / 3);
+    let reordered = [rest, first].concat();
+
+    let mut reader = PacketReader::new(CountingReader {
+        data: &reordered,
+        reads: 0,
+    });
+    let mut restored_data = Vec::new();
+    let error = reader.read_to_end(&mut restored_data).unwrap_err();
+
+    assert!(restored_data.is_empty());
+    let packet_error = error.get_ref().unwrap().downcast_ref::<PacketError>();
+    let packet_error = packet_error.unwrap();
+    assert_eq!(packet_error.kind(), &PacketErrorKind::SequencedPacket);
+    assert_eq!(packet_error.offset(), Some(0));
+    // The fixed header and the sequence are read separately before the packet is rejected.
+    assert_eq!(reader.get_ref().reads, 3);
+}
+
+#[test]
+fn test_reader_surfaces_packet_errors() {
+    let mut packet_data = "corrupted".to_packet_data(4);
+    packet_data[3] = 100;
+
+    let mut reader = PacketReader::new(&packet_data[..]);
+    let mut restored_data = Vec::new();
+    let error = reader.read_to_end(&mut restored_data).unwrap_err();
+
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let packet_error = error.get_ref().unwrap().downcast_ref::<PacketError>();
+    let packet_error = packet_error.unwrap();
+    assert!(matches!(
+        packet_error.kind(),
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+    assert_eq!(packet_error.offset(), Some(0));
+}
+
+#[test]
+fn test_reader_truncated_stream() {
+    let packet_data = "truncated".to_packet_data(4);
+
+    let mut reader = PacketReader::new(&packet_data[..packet_data.len() - 2]);
+    let mut restored_data = Vec::new();
+    let error = reader.read_to_end(&mut restored_data).unwrap_err();
+
+    assert_eq!(restored_data, b"truncate");
+    let packet_error = error.get_ref().unwrap().downcast_ref::<PacketError>();
+    let packet_error = packet_error.unwrap();
+    assert_eq!(
+        packet_error.kind(),
+        &PacketErrorKind::InvalidPacket {
+            declared: 7,
+            available: 5
+        }
+    );
+    assert_eq!(packet_error.offset(), Some(20));
+    assert_eq!(packet_error.packet_index(), Some(2));
+}
+
+#[test]
+fn test_reader_skips_parity_packets() {
+    let builder = PacketBuilder::new(4).framed().parity(2);
+    let mut packet_data = "with parity".to_packet_data_with(&builder);
+    packet_data.extend("again".to_packet_data_with(&builder));
+
+    let mut reader = PacketReader::new(&packet_data[..]);
+
+    assert_eq!(reader.read_message().unwrap().unwrap(), b"with parity");
+    assert_eq!(reader.read_message().unwrap().unwrap(), b"again");
+    assert_eq!(reader.read_message().unwrap(), None);
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn test_reader_rejects_compressed_packets() {
+    let builder = PacketBuilder::new(16).compression(Compression::Deflate);
+    let packet_data = "compressed ".repeat(10).to_packet_data_with(&builder);
+
+    let mut reader = PacketReader::new(&packet_data[..]);
+    let mut restored_data = Vec::new();
+    let error = reader.read_to_end(&mut restored_data).unwrap_err();
+
+    assert!(restored_data.is_empty());
+    let packet_error = error.get_ref().unwrap().downcast_ref::<PacketError>();
+    let packet_error = packet_error.unwrap();
+    assert_eq!(packet_error.kind(), &PacketErrorKind::CompressedPacket);
+    assert_eq!(packet_error.offset(), Some(0));
+}
@@ -0,0 +1,73 @@
+use solution::*;
+
+#[test]
+fn test_packet_iter_borrows_payloads() {
+    let packet_data = "borrowed".to_packet_data_with(&PacketBuilder::new(3));
+
+    let packets: Vec<Packet> = PacketIter::new(&packet_data)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let payloads: Vec<&[u8]> = packets.iter().map(|packet| packet.payload()).collect();
+
+    assert_eq!(payloads, vec![&b"bor"[..], b"row", b"ed"]);
+    let start = packet_data.as_ptr() as usize;
+    let payload_start = payloads[1].as_ptr() as usize;
+    assert!((start..start + packet_data.len()).contains(&payload_start));
+}
+
+#[test]
+fn test_packet_iter_stops_at_error() {
+    let mut packet_data = "abcdef".to_packet_data(2);
+    packet_data[8] = 9;
+
+    let mut packets = PacketIter::new(&packet_data);
+    assert_eq!(packets.next().unwrap().unwrap().payload(), b"ab");
+
+    let error = packets.next().unwrap().unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::UnknownProtocolVersion(9));
+    assert_eq!((error.offset(), error.packet_index()), (Some(8), Some(1)));
+    assert!(packets.next().is_none());
+    assert_eq!(packets.remainder(), &packet_data[8..]);
+}
+
+#[test]
+fn test_payload_chunks() {
+    let builder = PacketBuilder::new(4).sequenced(5);
+    let mut packets: Vec<Vec<u8>> = "in any order"
+        .to_packets_with(&builder)
+        .map(|packet| packet.serialize())
+        .collect();
+    packets.reverse();
+    let packet_data = packets.concat();
+
+    let mut chunks = PacketIter::new(&packet_data).payload_chunks().unwrap();
+    assert_eq!(chunks.remaining(), 12);
+    assert_eq!(chunks.chunk(), b"in a");
+
+    chunks.advance(6);
+    assert_eq!(chunks.chunk(), b" o");
+    let mut word = [0; 3];
+    chunks.copy_to_slice(&mut word);
+    assert_eq!(&word, b" or");
+
+    assert_eq!(chunks.collect::<Vec<_>>(), vec![&b"der"[..]]);
+}
+
+#[test]
+fn test_payload_chunks_skip_empty_payloads() {
+    let builder = PacketBuilder::new(4).framed();
+    let mut packet_data = "".to_packet_data_with(&builder);
+    packet_data.extend("text".to_packet_data_with(&builder));
+
+    let mut chunks = PacketIter::new(&packet_data).payload_chunks().unwrap();
+    assert_eq!(chunks.chunk(), b"text");
+    chunks.advance(4);
+    assert!(!chunks.has_remaining());
+    assert!(chunks.chunk().is_empty());
+
+    let packet_data = "missing".to_packet_data_with(&PacketBuilder::new(2).sequenced(1));
+    let error = PacketIter::new(&packet_data[16..])
+        .payload_chunks()
+        .unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::IncompleteMessage);
+}
@@ -0,0 +1,123 @@
+use solution::*;
+
+const LENGTHS: [MacLength; 4] = [
+    MacLength::Bytes4,
+    MacLength::Bytes8,
+    MacLength::Bytes16,
+    MacLength::Bytes32,
+];
+
+fn key(length: MacLength) -> MacKey {
+    MacKey::new(&[7; 32], length)
+}
+
+fn builder(length: MacLength) -> PacketBuilder {
+    PacketBuilder::new(8).framed().mac(key(length))
+}
+
+#[test]
+fn test_round_trip() {
+    let message = "integrity without secrecy";
+
+    for &length in &LENGTHS {
+        let packet_data = message.to_packet_data_with(&builder(length));
+        let (packet, _) = Packet::deserialize_with_key(&packet_data, &key(length)).unwrap();
+
+        assert_eq!(packet.mac_length(), Some(length));
+        assert_eq!(packet.checksum_algorithm(), None);
+        assert_eq!(packet.checksum().len(), length.tag_length());
+        assert_eq!(packet.encoded_len(), 4 + 8 + length.tag_length());
+
+        let opened = key(length).open(&packet_data).unwrap();
+        assert_eq!(String::from_packet_data(&opened).unwrap(), message);
+    }
+}
+
+#[test]
+fn test_tags_need_the_key() {
+    let packet_data = "tagged".to_packet_data_with(&builder(MacLength::Bytes16));
+
+    let error = Packet::deserialize(&packet_data).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::MissingKey);
+
+    let error = String::from_packet_data(&packet_data).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::MissingKey);
+    assert_eq!(error.offset(), Some(0));
+
+    // Parsing does not need the key, so damaged streams can still be inspected.
+    let (packet, rest) = Packet::deserialize_unverified(&packet_data).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(packet.payload(), b"tagged");
+}
+
+#[test]
+fn test_wrong_key_fails() {
+    let packet_data = "message".to_packet_data_with(&builder(MacLength::Bytes8));
+    let other = MacKey::new(&[8; 32], MacLength::Bytes8);
+
+    let error = Packet::deserialize_with_key(&packet_data, &other).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+}
+
+#[test]
+fn test_tampering_fails() {
+    let key = key(MacLength::Bytes8);
+    let packet_data = "two packets long".to_packet_data_with(&builder(MacLength::Bytes8));
+    let second = 4 + 8 + 8;
+
+    let mut changed_payload = packet_data.clone();
+    changed_payload[second + 4] ^= 1;
+    let error = key.open(&changed_payload).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+    assert_eq!(error.offset(), Some(second));
+    assert_eq!(error.packet_index(), Some(1));
+
+    // Unlike the checksum, the tag covers the header too.
+    let mut dropped_last = packet_data.clone();
+    dropped_last[second + 1] &= !0b0001_0000;
+    let error = key.open(&dropped_last).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+    assert_eq!(error.offset(), Some(second));
+}
+
+#[test]
+fn test_downgrades_fail() {
+    let key = key(MacLength::Bytes16);
+
+    let checksummed = "plain".to_packet_data_with(&PacketBuilder::new(8));
+    let error = Packet::deserialize_with_key(&checksummed, &key).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+
+    let shorter = "plain".to_packet_data_with(&builder(MacLength::Bytes4));
+    let error = key.open(&shorter).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::AuthenticationFailed);
+    assert_eq!(error.offset(), Some(0));
+}
+
+#[test]
+fn test_mac_needs_version_2() {
+    let builder = builder(MacLength::Bytes4).version(ProtocolVersion::V1);
+
+    assert_eq!(
+        builder.validate(),
+        Err(PacketErrorKind::UnsupportedByVersion.into())
+    );
+}
+
+#[test]
+fn test_debug_hides_key() {
+    let debug = format!("{:?}", builder(MacLength::Bytes32));
+
+    assert!(debug.contains("Bytes32"));
+    assert!(!debug.contains('7'));
+}
+
+#[test]
+fn test_compressed_messages() {
+    let text = "tagged and compressed ".repeat(50);
+    let builder = builder(MacLength::Bytes8).compression(Compression::Lz4);
+    let packet_data = text.to_packet_data_with(&builder);
+
+    let opened = key(MacLength::Bytes8).open(&packet_data).unwrap();
+    assert_eq!(String::from_packet_data(&opened).unwrap(), text);
+}
@@ -0,0 +1,217 @@
+use solution::*;
+
+const MESSAGE: &str = "abcdefghijklmnopqrstuvwxyz";
+
+fn builder() -> PacketBuilder {
+    PacketBuilder::new(4)
+        .checksum(ChecksumAlgorithm::Crc32)
+        .parity(3)
+}
+
+/// Start offsets and lengths of the packets in `packet_data`, parity packets included.
+fn packets(packet_data: &[u8]) -> Vec<(usize, usize, bool)> {
+    let mut packets = Vec::new();
+    let mut offset = 0;
+    for packet in PacketIter::new(packet_data) {
+        let packet = packet.unwrap();
+        packets.push((offset, packet.encoded_len(), packet.is_parity()));
+        offset += packet.encoded_len();
+    }
+    packets
+}
+
+fn data_packets(packet_data: &[u8]) -> Vec<(usize, usize)> {
+    packets(packet_data)
+        .into_iter()
+        .filter(|&(_, _, parity)| !parity)
+        .map(|(offset, length, _)| (offset, length))
+        .collect()
+}
+
+fn without(packet_data: &[u8], (offset, length): (usize, usize)) -> Vec<u8> {
+    let mut lost = packet_data[..offset].to_vec();
+    lost.extend_from_slice(&packet_data[(offset + length)..]);
+    lost
+}
+
+#[test]
+fn test_parity_packets() {
+    let packet_data = MESSAGE.to_packet_data_with(&builder());
+
+    let parity: Vec<bool> = packets(&packet_data)
+        .into_iter()
+        .map(|(_, _, parity)| parity)
+        .collect();
+    assert_eq!(
+        parity,
+        [false, false, false, true, false, false, false, true, false, true]
+    );
+    assert_eq!(String::from_packet_data(&packet_data).unwrap(), MESSAGE);
+    assert_eq!(
+        builder().packets(MESSAGE.as_bytes()).count(),
+        data_packets(&packet_data).len()
+    );
+}
+
+#[test]
+fn test_next_message_takes_trailing_parity() {
+    let builder = builder().framed();
+    let mut packet_data = MESSAGE.to_packet_data_with(&builder);
+    packet_data.extend("second".to_packet_data_with(&builder));
+
+    let (first, rest) = String::next_message(&packet_data).unwrap();
+    assert_eq!(first, MESSAGE);
+    let (second, rest) = String::next_message(rest).unwrap();
+    assert_eq!(second, "second");
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn test_damaged_packets_are_repaired() {
+    let packet_data = MESSAGE.to_packet_data_with(&builder());
+
+    for (offset, _) in data_packets(&packet_data) {
+        let mut damaged = packet_data.clone();
+        damaged[offset + 5] ^= 0xff;
+        assert_eq!(
+            String::from_packet_data_with_repair(&damaged).unwrap(),
+            MESSAGE
+        );
+
+        // A damaged size cannot even tell where the packet ends.
+        let mut damaged = packet_data.clone();
+        damaged[offset + 3] ^= 0x40;
+        assert_eq!(
+            String::from_packet_data_with_repair(&damaged).unwrap(),
+            MESSAGE
+        );
+    }
+}
+
+#[test]
+fn test_damaged_parity_packets_are_ignored() {
+    let packet_data = MESSAGE.to_packet_data_with(&builder());
+
+    for (offset, _, _) in packets(&packet_data).into_iter().filter(|p| p.2) {
+        let mut damaged = packet_data.clone();
+        damaged[offset + 6] ^= 1;
+        assert_eq!(
+            String::from_packet_data_with_repair(&damaged).unwrap(),
+            MESSAGE
+        );
+    }
+}
+
+#[test]
+fn test_data_packets_flagged_as_parity_are_not_dropped() {
+    let message = b"\x00hello world";
+
+    let packet_data = message.to_packet_data_with(&PacketBuilder::new(4));
+    for (offset, _) in data_packets(&packet_data) {
+        let mut damaged = packet_data.clone();
+        damaged[offset + 1] ^= 0b1000_0000;
+        let error = Vec::<u8>::from_packet_data_with_repair(&damaged).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            PacketErrorKind::InvalidChecksum { .. }
+        ));
+        assert_eq!(error.offset(), Some(offset));
+    }
+
+    // With parity packets of their own, they are rebuilt like any other damaged packet.
+    let packet_data = message.to_packet_data_with(&builder());
+    for (offset, _) in data_packets(&packet_data) {
+        let mut damaged = packet_data.clone();
+        damaged[offset + 1] ^= 0b1000_0000;
+        assert_eq!(
+            Vec::<u8>::from_packet_data_with_repair(&damaged).unwrap(),
+            message
+        );
+    }
+}
+
+#[test]
+fn test_lost_sequenced_packets_are_rebuilt() {
+    let builder = builder().sequenced(9);
+    let packet_data = MESSAGE.to_packet_data_with(&builder);
+
+    for packet in data_packets(&packet_data) {
+        let lost = without(&packet_data, packet);
+        assert_eq!(
+            String::from_packet_data_with_repair(&lost).unwrap(),
+            MESSAGE
+        );
+    }
+}
+
+#[test]
+fn test_lost_unsequenced_packets_are_reported() {
+    let packet_data = MESSAGE.to_packet_data_with(&builder());
+    let lost = without(&packet_data, data_packets(&packet_data)[1]);
+
+    let error = String::from_packet_data_with_repair(&lost).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::IncompleteMessage);
+    assert_eq!(error.packet_index(), Some(2));
+}
+
+#[test]
+fn test_two_damaged_packets_in_a_group() {
+    let packet_data = MESSAGE.to_packet_data_with(&builder());
+    let data = data_packets(&packet_data);
+    let mut damaged = packet_data.clone();
+    damaged[data[0].0 + 5] ^= 1;
+    damaged[data[2].0 + 5] ^= 1;
+
+    let error = String::from_packet_data_with_repair(&damaged).unwrap_err();
+    assert!(matches!(
+        error.kind(),
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+    assert_eq!(error.offset(), Some(0));
+
+    // Damage in separate groups is repaired.
+    let mut damaged = packet_data.clone();
+    damaged[data[0].0 + 5] ^= 1;
+    damaged[data[3].0 + 5] ^= 1;
+    assert_eq!(
+        String::from_packet_data_with_repair(&damaged).unwrap(),
+        MESSAGE
+    );
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn test_compressed_messages_are_repaired() {
+    let text = MESSAGE.repeat(20);
+    let builder = PacketBuilder::new(16)
+        .checksum(ChecksumAlgorithm::Crc32c)
+        .compression(Compression::Deflate)
+        .parity(4);
+    let packet_data = text.to_packet_data_with(&builder);
+    let mut damaged = packet_data.clone();
+    damaged[6] ^= 1;
+
+    assert_eq!(
+        String::from_packet_data_with_repair(&damaged).unwrap(),
+        text
+    );
+}
+
+#[test]
+fn test_parity_settings() {
+    assert_eq!(
+        builder().parity(0).validate(),
+        Err(PacketErrorKind::InvalidPacketSize.into())
+    );
+    assert_eq!(
+        PacketBuilder::new(65535).parity(1).validate(),
+        Err(PacketErrorKind::InvalidPacketSize.into())
+    );
+    assert_eq!(
+        PacketBuilder::new(4)
+            .version(ProtocolVersion::V1)
+            .parity(2)
+            .validate(),
+        Err(PacketErrorKind::UnsupportedByVersion.into())
+    );
+}
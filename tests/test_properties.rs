@@ -0,0 +1,208 @@
+//! Round-trip and corruption properties of the wire format.
+//!
+//! The version 2 checksum covers the header and the payload, the version 1 checksum only the
+//! payload. A single changed byte in a covered field or in the checksum is always caught,
+//! because every supported checksum changes when one byte of its input does. A changed
+//! version or version 1 size byte is caught because the stream stops lining up.
+
+use proptest::prelude::*;
+use solution::*;
+
+fn checksum_algorithm() -> impl Strategy<Value = ChecksumAlgorithm> {
+    prop_oneof![
+        Just(ChecksumAlgorithm::Additive),
+        Just(ChecksumAlgorithm::Crc32),
+        Just(ChecksumAlgorithm::Crc32c),
+        Just(ChecksumAlgorithm::Adler32),
+    ]
+}
+
+fn v2_builder() -> impl Strategy<Value = PacketBuilder> {
+    (
+        1..=2048u16,
+        checksum_algorithm(),
+        any::<Option<u16>>(),
+        any::<bool>(),
+    )
+        .prop_map(|(size, checksum, message_id, framed)| {
+            let mut builder = PacketBuilder::new(size).checksum(checksum);
+            if let Some(message_id) = message_id {
+                builder = builder.sequenced(message_id);
+            }
+            if framed {
+                builder = builder.framed();
+            }
+            builder
+        })
+}
+
+fn builder() -> impl Strategy<Value = PacketBuilder> {
+    prop_oneof![
+        (1..=255u16).prop_map(|size| PacketBuilder::new(size).version(ProtocolVersion::V1)),
+        v2_builder(),
+    ]
+}
+
+/// Where the payload and checksum of every packet start, and where the packet ends.
+fn packet_regions(packet_data: &[u8]) -> Vec<(usize, usize, usize)> {
+    let mut regions = Vec::new();
+    let mut offset = 0;
+
+    for packet in PacketIter::new(packet_data) {
+        let packet = packet.unwrap();
+        let end = offset + packet.encoded_len();
+        let payload_start = end - 4 - packet.payload().len();
+        regions.push((offset, payload_start, end));
+        offset = end;
+    }
+
+    regions
+}
+
+proptest! {
+    #[test]
+    fn prop_round_trip_v1(
+        data in proptest::collection::vec(any::<u8>(), 0..1024),
+        size in 1..=255u8,
+    ) {
+        let packet_data = data.to_packet_data(size);
+
+        prop_assert_eq!(Vec::<u8>::from_packet_data(&packet_data), Ok(data));
+    }
+
+    #[test]
+    fn prop_round_trip_strings(text in ".*", size in 1..=255u8) {
+        let packet_data = text.to_packet_data(size);
+
+        prop_assert_eq!(String::from_packet_data(&packet_data), Ok(text));
+    }
+
+    #[test]
+    fn prop_round_trip_any_builder(
+        data in proptest::collection::vec(any::<u8>(), 0..4096),
+        builder in builder(),
+    ) {
+        let packet_data = data.to_packet_data_with(&builder);
+
+        prop_assert_eq!(Vec::<u8>::from_packet_data(&packet_data), Ok(data));
+    }
+
+    #[test]
+    fn prop_payload_and_checksum_mutations_are_detected(
+        data in proptest::collection::vec(any::<u8>(), 1..1024),
+        builder in builder(),
+        position in any::<prop::sample::Index>(),
+        mask in 1..=255u8,
+    ) {
+        let mut packet_data = data.to_packet_data_with(&builder);
+        let covered: Vec<(usize, usize)> = packet_regions(&packet_data)
+            .into_iter()
+            .flat_map(|(start, payload_start, end)| {
+                (payload_start..end).map(move |index| (start, index))
+            })
+            .collect();
+        let (packet_start, index) = covered[position.index(covered.len())];
+        packet_data[index] ^= mask;
+
+        let error = Vec::<u8>::from_packet_data(&packet_data).unwrap_err();
+        prop_assert!(
+            matches!(error.kind(), PacketErrorKind::InvalidChecksum { .. }),
+            "unexpected error {}",
+            error
+        );
+        prop_assert_eq!(error.offset(), Some(packet_start));
+    }
+
+    #[test]
+    fn prop_v2_header_mutations_are_not_accepted(
+        data in proptest::collection::vec(any::<u8>(), 1..1024),
+        builder in v2_builder(),
+        position in any::<prop::sample::Index>(),
+        mask in 1..=255u8,
+    ) {
+        let mut packet_data = data.to_packet_data_with(&builder);
+        // The flags, size and sequence bytes, between the version byte and the payload.
+        let header_bytes: Vec<usize> = packet_regions(&packet_data)
+            .into_iter()
+            .flat_map(|(start, payload_start, _)| (start + 1)..payload_start)
+            .collect();
+        packet_data[header_bytes[position.index(header_bytes.len())]] ^= mask;
+
+        prop_assert!(Vec::<u8>::from_packet_data(&packet_data).is_err());
+    }
+
+    #[test]
+    fn prop_single_damaged_packet_is_repaired(
+        data in proptest::collection::vec(any::<u8>(), 1..1024),
+        size in 1..=64u16,
+        group_size in 1..=8u8,
+        message_id in any::<Option<u16>>(),
+        position in any::<prop::sample::Index>(),
+        mask in 1..=255u8,
+    ) {
+        let mut builder = PacketBuilder::new(size)
+            .checksum(ChecksumAlgorithm::Crc32)
+            .parity(group_size);
+        if let Some(message_id) = message_id {
+            builder = builder.sequenced(message_id);
+        }
+        let mut packet_data = data.to_packet_data_with(&builder);
+        let covered: Vec<usize> = packet_regions(&packet_data)
+            .into_iter()
+            .flat_map(|(_, payload_start, end)| payload_start..end)
+            .collect();
+        packet_data[covered[position.index(covered.len())]] ^= mask;
+
+        prop_assert_eq!(Vec::<u8>::from_packet_data_with_repair(&packet_data), Ok(data));
+    }
+
+    #[test]
+    fn prop_version_and_size_mutations_are_not_accepted(
+        data in proptest::collection::vec(any::<u8>(), 0..1024),
+        builder in builder(),
+        position in any::<prop::sample::Index>(),
+        mask in 1..=255u8,
+    ) {
+        let mut packet_data = data.to_packet_data_with(&builder);
+        // The version byte is followed by the size byte in version 1, and by the flags byte
+        // and two size bytes in version 2.
+        let header_bytes: Vec<usize> = packet_regions(&packet_data)
+            .into_iter()
+            .flat_map(|(start, _, _)| match packet_data[start] {
+                1 => vec![start, start + 1],
+                _ => vec![start, start + 2, start + 3],
+            })
+            .collect();
+        prop_assume!(!header_bytes.is_empty());
+        let index = header_bytes[position.index(header_bytes.len())];
+        packet_data[index] ^= mask;
+
+        prop_assert_ne!(Vec::<u8>::from_packet_data(&packet_data), Ok(data));
+    }
+
+    #[test]
+    fn prop_arbitrary_input_never_panics(
+        bytes in proptest::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let _ = Packet::deserialize(&bytes);
+        let _ = Vec::<u8>::from_packet_data(&bytes);
+        let _ = String::from_packet_data(&bytes);
+        let _ = from_packet_data_lossy(&bytes);
+        let _ = StreamDump::new(&bytes).to_string();
+
+        let mut decoder = PacketDecoder::new();
+        let mut failed = decoder.feed(&bytes).any(|result| result.is_err());
+        while failed {
+            decoder.resync();
+            failed = decoder.feed(&[]).any(|result| result.is_err());
+        }
+
+        let covered = Resync::new(&bytes)
+            .map(|recovered| match recovered {
+                Recovered::Packet(packet) => packet.encoded_len(),
+                Recovered::Skipped(skipped) => skipped.range.len(),
+            })
+            .sum::<usize>();
+        prop_assert_eq!(covered, bytes.len());
+    }
+}
@@ -0,0 +1,169 @@
+use solution::*;
+
+#[test]
+fn test_sequence_on_the_wire() {
+    let builder = PacketBuilder::new(4).sequenced(7);
+    let packets: Vec<Packet> = "sequenced".to_packets_with(&builder).collect();
+
+    assert_eq!(packets.len(), 3);
+    for (index, packet) in packets.iter().enumerate() {
+        let serialized = packet.serialize();
+        let (deserialized, _) = Packet::deserialize(&serialized).unwrap();
+
+        assert_eq!(
+            deserialized.sequence(),
+            Some(Sequence {
+                message_id: 7,
+                index: index as u16,
+                count: 3
+            })
+        );
+        assert_eq!(&deserialized, packet);
+    }
+}
+
+#[test]
+fn test_reassemble_out_of_order() {
+    let builder = PacketBuilder::new(3).sequenced(1);
+    let packets: Vec<Packet> = "out of order".to_packets_with(&builder).collect();
+    let mut reassembler = Reassembler::new();
+
+    for &index in [3, 0, 2].iter() {
+        assert_eq!(reassembler.push(&packets[index]), Ok(None));
+    }
+    assert_eq!(reassembler.missing(1), vec![1]);
+    assert_eq!(
+        reassembler.push(&packets[2]),
+        Err(PacketErrorKind::DuplicatePacket.into())
+    );
+
+    assert_eq!(
+        reassembler.push(&packets[1]),
+        Ok(Some(b"out of order".to_vec()))
+    );
+    assert_eq!(reassembler.pending(), 0);
+}
+
+#[test]
+fn test_reassemble_interleaved_messages() {
+    let first: Vec<Packet> = "first"
+        .to_packets_with(&PacketBuilder::new(2).sequenced(1))
+        .collect();
+    let second: Vec<Packet> = "second"
+        .to_packets_with(&PacketBuilder::new(2).sequenced(2))
+        .collect();
+    let mut reassembler = Reassembler::new();
+
+    assert_eq!(reassembler.push(&second[2]), Ok(None));
+    assert_eq!(reassembler.push(&first[1]), Ok(None));
+    assert_eq!(reassembler.push(&first[2]), Ok(None));
+    assert_eq!(reassembler.push(&second[0]), Ok(None));
+    assert_eq!(reassembler.push(&first[0]), Ok(Some(b"first".to_vec())));
+    assert_eq!(reassembler.pending(), 1);
+    assert_eq!(reassembler.push(&second[1]), Ok(Some(b"second".to_vec())));
+
+    let (unsequenced, _) = PacketBuilder::new(2).build(b"no");
+    assert_eq!(
+        reassembler.push(&unsequenced),
+        Err(PacketErrorKind::MissingSequence.into())
+    );
+}
+
+#[test]
+fn test_from_packet_data_reorders() {
+    let builder = PacketBuilder::new(4).sequenced(3);
+    let mut packets: Vec<Vec<u8>> = "shuffled packets"
+        .to_packets_with(&builder)
+        .map(|packet| packet.serialize())
+        .collect();
+    packets.swap(0, 3);
+    packets.swap(1, 2);
+
+    let packet_data = packets.concat();
+    assert_eq!(
+        String::from_packet_data(&packet_data).unwrap(),
+        "shuffled packets"
+    );
+
+    let packet_data = packets[1..].concat();
+    assert_eq!(
+        String::from_packet_data(&packet_data).unwrap_err().kind(),
+        &PacketErrorKind::IncompleteMessage
+    );
+}
+
+#[test]
+fn test_from_packet_data_rejects_impossible_counts() {
+    let mut packet_data = "x".to_packet_data_with(&PacketBuilder::new(4).sequenced(1));
+    packet_data[8..10].copy_from_slice(&u16::MAX.to_be_bytes());
+    let end = packet_data.len() - 4;
+    let checksum = Additive.checksum(&packet_data[..end]);
+    packet_data[end..].copy_from_slice(&checksum);
+
+    let error = String::from_packet_data(&packet_data).unwrap_err();
+    assert_eq!(error.kind(), &PacketErrorKind::IncompleteMessage);
+    assert_eq!(error.offset(), Some(0));
+}
+
+#[test]
+fn test_reassembler_limits_pending_messages() {
+    let mut reassembler = Reassembler::new();
+    let first_packet = |message_id: u16| {
+        let builder = PacketBuilder::new(1).sequenced(message_id);
+        "ab".to_packets_with(&builder).next().unwrap()
+    };
+
+    for message_id in 0..MAX_PENDING_MESSAGES as u16 {
+        assert_eq!(reassembler.push(&first_packet(message_id)), Ok(None));
+    }
+    assert_eq!(
+        reassembler.push(&first_packet(u16::MAX)),
+        Err(PacketErrorKind::TooManyPendingMessages.into())
+    );
+
+    let second: Vec<Packet> = "ab"
+        .to_packets_with(&PacketBuilder::new(1).sequenced(0))
+        .collect();
+    assert_eq!(reassembler.push(&second[1]), Ok(Some(b"ab".to_vec())));
+    assert_eq!(reassembler.missing(1), vec![1]);
+}
+
+#[test]
+fn test_reassembler_discards_pending_messages() {
+    let packets: Vec<Packet> = "lost"
+        .to_packets_with(&PacketBuilder::new(1).sequenced(5))
+        .collect();
+    let mut reassembler = Reassembler::new();
+
+    assert_eq!(reassembler.push(&packets[0]), Ok(None));
+    assert!(reassembler.discard(5));
+    assert!(!reassembler.discard(5));
+    assert_eq!(reassembler.pending(), 0);
+    assert_eq!(reassembler.missing(5), Vec::<u16>::new());
+
+    // The packets pushed before do not count towards the message anymore.
+    for packet in &packets[1..] {
+        assert_eq!(reassembler.push(packet), Ok(None));
+    }
+    assert_eq!(reassembler.push(&packets[0]), Ok(Some(b"lost".to_vec())));
+}
+
+#[test]
+fn test_reassembler_rejects_late_duplicates() {
+    let builder = PacketBuilder::new(2).sequenced(3);
+    let packets: Vec<Packet> = "late".to_packets_with(&builder).collect();
+    let mut reassembler = Reassembler::new();
+
+    assert_eq!(reassembler.push(&packets[0]), Ok(None));
+    assert_eq!(reassembler.push(&packets[1]), Ok(Some(b"late".to_vec())));
+    assert_eq!(
+        reassembler.push(&packets[1]),
+        Err(PacketErrorKind::DuplicatePacket.into())
+    );
+    assert_eq!(reassembler.pending(), 0);
+
+    // A different message can still reuse the id.
+    let packets: Vec<Packet> = "next".to_packets_with(&builder).collect();
+    assert_eq!(reassembler.push(&packets[1]), Ok(None));
+    assert_eq!(reassembler.push(&packets[0]), Ok(Some(b"next".to_vec())));
+}
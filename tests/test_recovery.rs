@@ -0,0 +1,161 @@
+use std::ops::Range;
+
+use solution::*;
+
+fn payloads_and_skips(packet_data: &[u8]) -> (Vec<u8>, Vec<(Range<usize>, PacketErrorKind)>) {
+    let mut payloads = Vec::new();
+    let mut skips = Vec::new();
+
+    for recovered in Resync::new(packet_data) {
+        match recovered {
+            Recovered::Packet(packet) => payloads.extend_from_slice(packet.payload()),
+            Recovered::Skipped(skipped) => {
+                skips.push((skipped.range, skipped.error.kind().clone()))
+            }
+        }
+    }
+
+    (payloads, skips)
+}
+
+#[test]
+fn test_resync_skips_bad_checksum() {
+    let builder = PacketBuilder::new(4).checksum(ChecksumAlgorithm::Crc32);
+    let mut packet_data = "aaaabbbbcccc".to_packet_data_with(&builder);
+    packet_data[17] ^= 0xFF;
+
+    let (payloads, skips) = payloads_and_skips(&packet_data);
+
+    assert_eq!(payloads, b"aaaacccc");
+    assert_eq!(skips.len(), 1);
+    assert_eq!(skips[0].0, 12..24);
+    assert!(matches!(
+        skips[0].1,
+        PacketErrorKind::InvalidChecksum { .. }
+    ));
+}
+
+#[test]
+fn test_resync_skips_noise() {
+    let mut packet_data = "first".to_packet_data(8);
+    packet_data.extend_from_slice(&[0xFF, 0x17, 0x42]);
+    packet_data.extend("second".to_packet_data(8));
+
+    let (payloads, skips) = payloads_and_skips(&packet_data);
+
+    assert_eq!(payloads, b"firstsecond");
+    assert_eq!(
+        skips,
+        vec![(11..14, PacketErrorKind::UnknownProtocolVersion(0xFF))]
+    );
+}
+
+#[test]
+fn test_resync_truncated_tail() {
+    let packet_data = "tail".to_packet_data(2);
+    let length = packet_data.len();
+
+    let (payloads, skips) = payloads_and_skips(&packet_data[..length - 1]);
+
+    assert_eq!(payloads, b"ta");
+    assert_eq!(
+        skips,
+        vec![(
+            8..(length - 1),
+            PacketErrorKind::InvalidPacket {
+                declared: 8,
+                available: 7
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_decoder_resync() {
+    let mut packet_data = "ok".to_packet_data(4);
+    packet_data.extend_from_slice(&[0xAA, 0xBB]);
+    packet_data.extend("fine".to_packet_data(4));
+
+    let mut decoder = PacketDecoder::new();
+    let mut payloads = Vec::new();
+    let mut skips = Vec::new();
+
+    for chunk in packet_data.chunks(3) {
+        let mut results: Vec<_> = decoder.feed(chunk).collect();
+        loop {
+            let mut failed = false;
+            for result in results {
+                match result {
+                    Ok(packet) => payloads.extend_from_slice(packet.payload()),
+                    Err(_) => failed = true,
+                }
+            }
+            if !failed {
+                break;
+            }
+            skips.extend(decoder.resync());
+            results = decoder.feed(&[]).collect();
+        }
+    }
+
+    // The noise arrives in two chunks, so it is dropped in two steps.
+    assert_eq!(payloads, b"okfine");
+    let skips: Vec<_> = skips
+        .iter()
+        .map(|skipped| {
+            (
+                skipped.range.clone(),
+                skipped.error.kind(),
+                skipped.error.offset(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        skips,
+        vec![
+            (
+                8..9,
+                &PacketErrorKind::UnknownProtocolVersion(0xAA),
+                Some(8)
+            ),
+            (
+                9..10,
+                &PacketErrorKind::UnknownProtocolVersion(0xBB),
+                Some(9)
+            )
+        ]
+    );
+}
+
+#[test]
+fn test_decoder_resync_prefers_complete_packets() {
+    // The noise ends in what looks like the header of a 64 KiB packet.
+    let mut packet_data = vec![0xAA, 0x02, 0x00, 0xFF, 0xFF];
+    packet_data.extend("after the noise".to_packet_data_with(&PacketBuilder::new(8)));
+
+    let mut decoder = PacketDecoder::new();
+    assert!(decoder.feed(&packet_data).next().unwrap().is_err());
+    let skipped = decoder.resync().unwrap();
+    let payloads: Vec<u8> = decoder
+        .feed(&[])
+        .flat_map(|packet| packet.unwrap().payload().to_vec())
+        .collect();
+
+    assert_eq!(skipped.range, 0..5);
+    assert_eq!(payloads, b"after the noise");
+}
+
+#[test]
+fn test_decoder_resync_waits_for_incomplete_packets() {
+    let packet_data = "partial".to_packet_data_with(&PacketBuilder::new(16));
+    let mut noisy_data = vec![0xAA];
+    noisy_data.extend_from_slice(&packet_data[..6]);
+
+    let mut decoder = PacketDecoder::new();
+    assert!(decoder.feed(&noisy_data).next().unwrap().is_err());
+    assert_eq!(decoder.resync().unwrap().range, 0..1);
+    let packets: Vec<_> = decoder.feed(&packet_data[6..]).collect();
+
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].as_ref().unwrap().payload(), b"partial");
+}
@@ -0,0 +1,70 @@
+use solution::*;
+
+#[test]
+fn test_serialize_into_matches_serialize() {
+    let builders = [
+        PacketBuilder::new(8).version(ProtocolVersion::V1),
+        PacketBuilder::new(8).checksum(ChecksumAlgorithm::Crc32c),
+        PacketBuilder::new(8).sequenced(4).framed(),
+    ];
+
+    for builder in builders.iter() {
+        let (packet, _) = builder.build(b"fixed buffer");
+        let mut buf = [0xAA; 64];
+
+        let length = packet.serialize_into(&mut buf).unwrap();
+        assert_eq!(length, packet.encoded_len());
+        assert_eq!(&buf[..length], &packet.serialize()[..]);
+        assert!(buf[length..].iter().all(|&byte| byte == 0xAA));
+    }
+}
+
+#[test]
+fn test_serialize_into_short_buffer() {
+    let (packet, _) = PacketBuilder::new(16).build(b"payload");
+    let mut buf = [0; 14];
+
+    assert_eq!(
+        packet.serialize_into(&mut buf),
+        Err(PacketErrorKind::BufferTooSmall {
+            required: 15,
+            available: 14
+        }
+        .into())
+    );
+    assert_eq!(buf, [0; 14]);
+}
+
+#[test]
+fn test_write_packet_data_reuses_buffer() {
+    let builder = PacketBuilder::new(3).framed();
+    let mut out = Vec::new();
+
+    String::from("first")
+        .write_packet_data_with(&builder, &mut out)
+        .unwrap();
+    b"second"
+        .to_vec()
+        .write_packet_data_with(&builder, &mut out)
+        .unwrap();
+    "third".write_packet_data_with(&builder, &mut out).unwrap();
+
+    let (first, rest) = String::next_message(&out).unwrap();
+    let (second, rest) = Vec::<u8>::next_message(rest).unwrap();
+    let (third, rest) = String::next_message(rest).unwrap();
+    assert_eq!(
+        (first.as_str(), &second[..], third.as_str()),
+        ("first", &b"second"[..], "third")
+    );
+    assert!(rest.is_empty());
+
+    let mut out = Vec::new();
+    String::from("legacy")
+        .write_packet_data(4, &mut out)
+        .unwrap();
+    assert_eq!(out, String::from("legacy").to_packet_data(4));
+    assert!(String::from("legacy")
+        .write_packet_data(0, &mut out)
+        .is_err());
+    assert_eq!(out, String::from("legacy").to_packet_data(4));
+}